@@ -0,0 +1,69 @@
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// Size of the huge pages requested with `MAP_HUGETLB`
+pub const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Anonymous mapping of `u32` elements backed by 2 MiB huge pages
+pub struct HugePageBuffer {
+    ptr: NonNull<u32>,
+    len: usize,
+    mapped_bytes: usize,
+}
+
+impl HugePageBuffer {
+    /// Maps `len` zeroed elements on huge pages, returning `None` if the kernel refuses the
+    /// mapping (e.g. no huge pages reserved in `/proc/sys/vm/nr_hugepages`).
+    pub fn new(len: usize) -> Option<Self> {
+        // MAP_HUGETLB mappings must span a whole number of huge pages
+        let mapped_bytes = (len * std::mem::size_of::<u32>())
+            .max(1)
+            .next_multiple_of(HUGE_PAGE_SIZE);
+
+        // SAFETY: anonymous private mapping with no address hint
+        let raw = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                mapped_bytes,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_HUGETLB,
+                -1,
+                0,
+            )
+        };
+        if raw == libc::MAP_FAILED {
+            return None;
+        }
+
+        Some(HugePageBuffer {
+            ptr: NonNull::new(raw as *mut u32)?,
+            len,
+            mapped_bytes,
+        })
+    }
+}
+
+impl Deref for HugePageBuffer {
+    type Target = [u32];
+
+    fn deref(&self) -> &[u32] {
+        // SAFETY: the mapping holds at least `len` zero-initialized u32s
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for HugePageBuffer {
+    fn deref_mut(&mut self) -> &mut [u32] {
+        // SAFETY: the mapping holds at least `len` zero-initialized u32s owned by this buffer
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for HugePageBuffer {
+    fn drop(&mut self) {
+        // SAFETY: ptr and mapped_bytes describe a mapping created by mmap in `new`
+        unsafe {
+            libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.mapped_bytes);
+        }
+    }
+}
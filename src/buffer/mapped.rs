@@ -0,0 +1,94 @@
+use std::fs::OpenOptions;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::ptr::NonNull;
+
+/// Shared mapping of `u32` elements from a file, such as one on tmpfs or a persistent-memory
+/// DAX filesystem, or a device DAX node like `/dev/dax0.0`. Stores go to the file, so write
+/// kernels overwrite its contents.
+pub struct MappedFile {
+    ptr: NonNull<u32>,
+    len: usize,
+    mapped_bytes: usize,
+}
+
+/// Bytes reserved for each region of `len` elements, rounded up to whole pages since mmap
+/// offsets must be page aligned
+fn region_bytes(len: usize) -> usize {
+    // SAFETY: sysconf has no preconditions
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as usize;
+    (len * std::mem::size_of::<u32>())
+        .max(1)
+        .next_multiple_of(page)
+}
+
+impl MappedFile {
+    /// Maps `len` elements from the `index`th page-aligned region of `path`, so threads given
+    /// distinct indices never share a page. A regular file is created if absent and grown to
+    /// cover the region; device files must already be large enough.
+    pub fn map(path: &Path, index: usize, len: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mapped_bytes = (len * std::mem::size_of::<u32>()).max(1);
+        let offset = (index * region_bytes(len)) as u64;
+        let metadata = file.metadata()?;
+        // Growing only, so a region another thread already sized is never cut short
+        if metadata.is_file() && metadata.len() < offset + mapped_bytes as u64 {
+            file.set_len(offset + mapped_bytes as u64)?;
+        }
+
+        // SAFETY: shared mapping of an open file at a page-aligned offset with no address
+        // hint; the mapping outlives the descriptor, which mmap does not need to stay open
+        let raw = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                mapped_bytes,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                offset as libc::off_t,
+            )
+        };
+        if raw == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(MappedFile {
+            ptr: NonNull::new(raw as *mut u32).ok_or_else(|| io::Error::other("null mapping"))?,
+            len,
+            mapped_bytes,
+        })
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u32];
+
+    fn deref(&self) -> &[u32] {
+        // SAFETY: the mapping holds at least `len` u32s backed by the file, and every bit
+        // pattern is a valid u32
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for MappedFile {
+    fn deref_mut(&mut self) -> &mut [u32] {
+        // SAFETY: the mapping holds at least `len` u32s
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        // SAFETY: ptr and mapped_bytes describe a mapping created by mmap in `map`
+        unsafe {
+            libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.mapped_bytes);
+        }
+    }
+}
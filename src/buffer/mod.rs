@@ -0,0 +1,371 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+#[cfg(target_os = "linux")]
+mod hugepage;
+
+#[cfg(target_os = "linux")]
+mod mapped;
+#[cfg(target_os = "linux")]
+mod wc;
+
+#[cfg(target_os = "linux")]
+pub use hugepage::HugePageBuffer;
+#[cfg(target_os = "linux")]
+pub use mapped::MappedFile;
+#[cfg(target_os = "linux")]
+pub use wc::WcBuffer;
+
+/// Memory type of the benchmark buffers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MemType {
+    /// Ordinary cacheable memory
+    #[default]
+    Normal,
+    /// Write-combining memory mapped from a device file (Linux only)
+    Wc,
+}
+
+/// Per-thread benchmark buffer: heap allocated, backed by huge pages, mapped write-combining
+/// from a device file, or mapped from a file
+pub enum Buffer {
+    Aligned(AlignedBuffer),
+    #[cfg(target_os = "linux")]
+    HugePages(HugePageBuffer),
+    #[cfg(target_os = "linux")]
+    WriteCombining(WcBuffer),
+    #[cfg(target_os = "linux")]
+    File(MappedFile),
+}
+
+impl Buffer {
+    /// Allocates `len` elements, trying huge pages first when requested and falling back to
+    /// an aligned heap allocation if they are unavailable or coarser than a huge page
+    pub fn allocate(len: usize, align: usize, hugepages: bool) -> Self {
+        #[cfg(target_os = "linux")]
+        if hugepages && align <= hugepage::HUGE_PAGE_SIZE {
+            if let Some(buffer) = HugePageBuffer::new(len) {
+                return Buffer::HugePages(buffer);
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = hugepages;
+
+        Buffer::Aligned(AlignedBuffer::new(len, align))
+    }
+
+    /// Maps `len` elements of write-combining memory from `path`, see `WcBuffer`
+    pub fn map_write_combining(path: &std::path::Path, len: usize) -> std::io::Result<Self> {
+        #[cfg(target_os = "linux")]
+        return WcBuffer::map(path, len).map(Buffer::WriteCombining);
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (path, len);
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "write-combining memory is only supported on Linux",
+            ))
+        }
+    }
+
+    /// Maps `len` elements from the `index`th region of the file at `path`, see `MappedFile`
+    pub fn map_file(path: &std::path::Path, index: usize, len: usize) -> std::io::Result<Self> {
+        #[cfg(target_os = "linux")]
+        return MappedFile::map(path, index, len).map(Buffer::File);
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (path, index, len);
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "file-backed buffers are only supported on Linux",
+            ))
+        }
+    }
+
+    pub fn is_huge_pages(&self) -> bool {
+        match self {
+            Buffer::Aligned(_) => false,
+            #[cfg(target_os = "linux")]
+            Buffer::HugePages(_) => true,
+            #[cfg(target_os = "linux")]
+            Buffer::WriteCombining(_) => false,
+            #[cfg(target_os = "linux")]
+            Buffer::File(_) => false,
+        }
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [u32];
+
+    fn deref(&self) -> &[u32] {
+        match self {
+            Buffer::Aligned(buffer) => buffer,
+            #[cfg(target_os = "linux")]
+            Buffer::HugePages(buffer) => buffer,
+            #[cfg(target_os = "linux")]
+            Buffer::WriteCombining(buffer) => buffer,
+            #[cfg(target_os = "linux")]
+            Buffer::File(buffer) => buffer,
+        }
+    }
+}
+
+impl DerefMut for Buffer {
+    fn deref_mut(&mut self) -> &mut [u32] {
+        match self {
+            Buffer::Aligned(buffer) => buffer,
+            #[cfg(target_os = "linux")]
+            Buffer::HugePages(buffer) => buffer,
+            #[cfg(target_os = "linux")]
+            Buffer::WriteCombining(buffer) => buffer,
+            #[cfg(target_os = "linux")]
+            Buffer::File(buffer) => buffer,
+        }
+    }
+}
+
+/// Heap buffer of `u32` elements whose start address is aligned to a caller-chosen boundary
+pub struct AlignedBuffer {
+    ptr: NonNull<u32>,
+    len: usize,
+    layout: Layout,
+}
+
+impl AlignedBuffer {
+    /// Allocates a zeroed buffer of `len` elements aligned to `align` bytes.
+    /// `align` must be a power of two and at least the alignment of `u32`.
+    pub fn new(len: usize, align: usize) -> Self {
+        assert!(
+            align.is_power_of_two() && align >= std::mem::align_of::<u32>(),
+            "alignment must be a power of two and at least 4 bytes"
+        );
+        let layout = Layout::array::<u32>(len)
+            .and_then(|layout| layout.align_to(align))
+            .expect("buffer size overflows the address space");
+
+        if layout.size() == 0 {
+            // A dangling pointer still has to honour the alignment
+            return AlignedBuffer {
+                ptr: NonNull::new(std::ptr::without_provenance_mut(layout.align())).unwrap(),
+                len,
+                layout,
+            };
+        }
+
+        // SAFETY: the layout has a non-zero size
+        let raw = unsafe { alloc_zeroed(layout) };
+        let ptr = NonNull::new(raw as *mut u32).unwrap_or_else(|| handle_alloc_error(layout));
+
+        AlignedBuffer { ptr, len, layout }
+    }
+}
+
+impl Deref for AlignedBuffer {
+    type Target = [u32];
+
+    fn deref(&self) -> &[u32] {
+        // SAFETY: ptr points to `len` zero-initialized u32s owned by this buffer
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for AlignedBuffer {
+    fn deref_mut(&mut self) -> &mut [u32] {
+        // SAFETY: ptr points to `len` zero-initialized u32s uniquely owned by this buffer
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            // SAFETY: ptr was allocated by alloc_zeroed with this exact layout
+            unsafe { dealloc(self.ptr.as_ptr() as *mut u8, self.layout) };
+        }
+    }
+}
+
+/// Returns the size of a base memory page in bytes
+pub fn page_size() -> usize {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: sysconf has no preconditions
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if size > 0 {
+            return size as usize;
+        }
+    }
+    4096
+}
+
+/// Touches one element per page so every page of the buffer is resident before timing starts
+pub fn prefault(data: &mut [u32]) {
+    let step = (page_size() / std::mem::size_of::<u32>()).max(1);
+    for i in (0..data.len()).step_by(step) {
+        // SAFETY: i is in bounds; volatile keeps the rewrite of the same value from being elided
+        unsafe {
+            let ptr = data.as_mut_ptr().add(i);
+            std::ptr::write_volatile(ptr, std::ptr::read_volatile(ptr));
+        }
+    }
+}
+
+/// Initial contents of each thread's source buffer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fill {
+    #[default]
+    Random,
+    Zero,
+    /// Each element holds its own index
+    Sequential,
+    Constant(u32),
+}
+
+impl Fill {
+    /// Writes the pattern into `data`. Buffers are allocated zeroed, so `Zero` writes nothing.
+    pub fn apply(&self, data: &mut [u32]) {
+        match *self {
+            Fill::Random => {
+                let mut rng = rand::rng();
+                data.iter_mut().for_each(|x| *x = rng.random());
+            }
+            Fill::Zero => {}
+            Fill::Sequential => data.iter_mut().enumerate().for_each(|(i, x)| *x = i as u32),
+            Fill::Constant(value) => data.fill(value),
+        }
+    }
+}
+
+/// Parses a `--fill` value: `random`, `zero`, `sequential` or a `0x`-prefixed hex constant
+pub fn parse_fill(s: &str) -> Result<Fill, String> {
+    match s {
+        "random" => Ok(Fill::Random),
+        "zero" => Ok(Fill::Zero),
+        "sequential" => Ok(Fill::Sequential),
+        _ => s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .ok_or_else(|| {
+                format!(
+                    "{} is not random, zero, sequential or a 0x-prefixed hex constant",
+                    s
+                )
+            })
+            .and_then(|hex| u32::from_str_radix(hex, 16).map_err(|e| format!("{}", e)))
+            .map(Fill::Constant),
+    }
+}
+
+/// Parses an `--align` value, rejecting anything that is not a power of two of at least 4 bytes
+pub fn parse_alignment(s: &str) -> Result<usize, String> {
+    let align: usize = s.parse().map_err(|e| format!("{}", e))?;
+    if !align.is_power_of_two() || align < std::mem::align_of::<u32>() {
+        return Err(format!(
+            "{} is not a power of two of at least 4 bytes",
+            align
+        ));
+    }
+    Ok(align)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_fresh_buffers_with_each_pattern() {
+        let patterns = [
+            (Fill::Zero, vec![0; 5]),
+            (Fill::Sequential, vec![0, 1, 2, 3, 4]),
+            (Fill::Constant(0xdeadbeef), vec![0xdeadbeef; 5]),
+        ];
+        for (fill, expected) in patterns {
+            let mut buffer = Buffer::allocate(5, 64, false);
+            fill.apply(&mut buffer);
+            assert_eq!(&buffer[..], &expected[..], "{:?}", fill);
+        }
+
+        // Random fills differ from the zeroed allocation and from each other
+        let mut first = Buffer::allocate(1024, 64, false);
+        let mut second = Buffer::allocate(1024, 64, false);
+        Fill::Random.apply(&mut first);
+        Fill::Random.apply(&mut second);
+        assert!(first.iter().any(|&x| x != 0));
+        assert_ne!(&first[..], &second[..]);
+    }
+
+    fn is_aligned(buffer: &[u32], align: usize) -> bool {
+        (buffer.as_ptr() as usize).is_multiple_of(align)
+    }
+
+    #[test]
+    fn heap_and_huge_page_buffers_honour_the_alignment() {
+        for shift in 2..=22 {
+            let align = 1 << shift;
+            for len in [0, 1, 1000, 4096] {
+                let buffer = Buffer::allocate(len, align, false);
+                assert!(is_aligned(&buffer, align), "align {} len {}", align, len);
+                assert_eq!(buffer.len(), len);
+
+                // Falls back to the heap when no huge pages are reserved
+                let buffer = Buffer::allocate(len, align, true);
+                assert!(
+                    is_aligned(&buffer, align),
+                    "huge, align {} len {}",
+                    align,
+                    len
+                );
+                assert_eq!(buffer.len(), len);
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn mapped_buffers_are_page_aligned() {
+        // Mappings start on a page, which satisfies any --align up to the page size
+        let path = std::env::temp_dir().join(format!("mallow-align-{}", std::process::id()));
+        let mut mapped: Vec<_> = (0..3)
+            .map(|index| Buffer::map_file(&path, index, 1000))
+            .collect();
+        // The file map_file created stands in for a device file
+        mapped.push(Buffer::map_write_combining(&path, 1000));
+        std::fs::remove_file(&path).unwrap();
+
+        for buffer in mapped {
+            let buffer = buffer.unwrap();
+            assert!(is_aligned(&buffer, page_size()));
+            assert_eq!(buffer.len(), 1000);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "power of two")]
+    fn rejects_alignments_that_are_not_powers_of_two() {
+        AlignedBuffer::new(16, 48);
+    }
+
+    #[test]
+    fn parses_alignments() {
+        assert_eq!(parse_alignment("4"), Ok(4));
+        assert_eq!(parse_alignment("4096"), Ok(4096));
+        assert!(parse_alignment("2").is_err());
+        assert!(parse_alignment("48").is_err());
+        assert!(parse_alignment("0").is_err());
+    }
+
+    #[test]
+    fn parses_fill_patterns() {
+        assert_eq!(parse_fill("zero"), Ok(Fill::Zero));
+        assert_eq!(parse_fill("0xDEADbeef"), Ok(Fill::Constant(0xdeadbeef)));
+        assert_eq!(parse_fill("0X10"), Ok(Fill::Constant(16)));
+        assert!(parse_fill("deadbeef").is_err());
+        assert!(parse_fill("0x1_0000_0000").is_err());
+    }
+}
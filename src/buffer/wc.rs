@@ -0,0 +1,87 @@
+use std::fs::OpenOptions;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::ptr::NonNull;
+
+/// Shared mapping of `u32` elements from a file whose mappings the kernel makes
+/// write-combining, such as a PCI BAR's `/sys/bus/pci/devices/<device>/resource<N>_wc` or a
+/// driver's device node. Linux has no way to request write-combining for ordinary anonymous
+/// memory from user space, so the memory type comes from whatever backs `path`. Opening
+/// sysfs resources needs root.
+pub struct WcBuffer {
+    ptr: NonNull<u32>,
+    len: usize,
+    mapped_bytes: usize,
+}
+
+impl WcBuffer {
+    /// Maps the first `len` elements of `path`. Every mapping of the same file shares its
+    /// contents, so threads given the same path write the same region.
+    pub fn map(path: &Path, len: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mapped_bytes = (len * std::mem::size_of::<u32>()).max(1);
+        // Device files such as sysfs resources report the region size as their length
+        let available = file.metadata()?.len();
+        if available != 0 && available < mapped_bytes as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is only {} bytes, smaller than the {} byte buffer",
+                    path.display(),
+                    available,
+                    mapped_bytes
+                ),
+            ));
+        }
+
+        // SAFETY: shared mapping of an open file with no address hint; the mapping outlives
+        // the descriptor, which mmap does not need to stay open
+        let raw = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                mapped_bytes,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if raw == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(WcBuffer {
+            ptr: NonNull::new(raw as *mut u32).ok_or_else(|| io::Error::other("null mapping"))?,
+            len,
+            mapped_bytes,
+        })
+    }
+}
+
+impl Deref for WcBuffer {
+    type Target = [u32];
+
+    fn deref(&self) -> &[u32] {
+        // SAFETY: the mapping holds at least `len` u32s; device memory may read back anything,
+        // and every bit pattern is a valid u32
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for WcBuffer {
+    fn deref_mut(&mut self) -> &mut [u32] {
+        // SAFETY: the mapping holds at least `len` u32s
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for WcBuffer {
+    fn drop(&mut self) {
+        // SAFETY: ptr and mapped_bytes describe a mapping created by mmap in `map`
+        unsafe {
+            libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.mapped_bytes);
+        }
+    }
+}
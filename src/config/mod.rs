@@ -0,0 +1,207 @@
+//! `--config` files: a flat table of long option names and values, applied underneath the
+//! command line so that anything given there wins.
+
+use clap::parser::ValueSource;
+use clap::{Arg, Command};
+use std::path::Path;
+
+mod toml;
+
+/// A single value from a config file
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+/// Options read from a config file, keyed by long option name (`_` or `-` separated) in file
+/// order
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigFile {
+    pub entries: Vec<(String, ConfigValue)>,
+}
+
+impl ConfigFile {
+    /// Reads a TOML file, or a JSON object when the file name ends in `.json`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let is_json = path.extension().is_some_and(|ext| ext == "json");
+        let parsed = if is_json {
+            Self::parse_json(&content)
+        } else {
+            toml::parse(&content).map(|entries| ConfigFile { entries })
+        };
+        parsed.map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    fn parse_json(content: &str) -> Result<Self, String> {
+        let values: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(content).map_err(|e| e.to_string())?;
+        let mut entries = Vec::new();
+        for (key, value) in values {
+            let value = match value {
+                serde_json::Value::Null => continue,
+                serde_json::Value::Bool(b) => ConfigValue::Bool(b),
+                serde_json::Value::String(s) => ConfigValue::String(s),
+                serde_json::Value::Number(n) => match n.as_i64() {
+                    Some(i) => ConfigValue::Integer(i),
+                    None => ConfigValue::Float(n.as_f64().unwrap_or(f64::NAN)),
+                },
+                _ => return Err(format!("{} must be a string, number or bool", key)),
+            };
+            entries.push((key, value));
+        }
+        Ok(ConfigFile { entries })
+    }
+
+    /// Merges the file underneath `argv`. An option is dropped when the command line sets it,
+    /// or sets an option it conflicts with (`--iterations` replaces a file's `duration`), and
+    /// the rest are passed to clap ahead of the real arguments so they are validated the same
+    /// way.
+    pub fn merge_under(
+        &self,
+        command: &Command,
+        mut argv: Vec<String>,
+    ) -> Result<Vec<String>, String> {
+        let explicit = explicit_args(command, &argv);
+
+        let mut from_file = Vec::new();
+        for (key, value) in &self.entries {
+            let id = key.replace('-', "_");
+            let arg = command
+                .get_arguments()
+                .find(|arg| arg.get_id() == id.as_str() && arg.get_long().is_some())
+                .ok_or_else(|| format!("Unknown option in config file: {}", key))?;
+            if explicit.iter().any(|given| overrides(command, given, arg)) {
+                continue;
+            }
+
+            let flag = format!("--{}", arg.get_long().unwrap());
+            match value {
+                ConfigValue::Bool(true) => from_file.push(flag),
+                ConfigValue::Bool(false) => {}
+                ConfigValue::Integer(n) => from_file.extend([flag, n.to_string()]),
+                ConfigValue::Float(f) => from_file.extend([flag, f.to_string()]),
+                ConfigValue::String(s) => from_file.extend([flag, s.clone()]),
+            }
+        }
+
+        argv.splice(1..1, from_file);
+        Ok(argv)
+    }
+}
+
+/// Arguments `argv` gives explicitly. Errors are ignored here since the full parse reports
+/// them, and a missing requirement may still be met by the config file.
+fn explicit_args<'a>(command: &'a Command, argv: &[String]) -> Vec<&'a Arg> {
+    let Ok(matches) = command
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(argv)
+    else {
+        return Vec::new();
+    };
+    command
+        .get_arguments()
+        .filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .collect()
+}
+
+/// Whether giving `given` on the command line means the file's `arg` must be dropped
+fn overrides(command: &Command, given: &Arg, arg: &Arg) -> bool {
+    given.get_id() == arg.get_id()
+        || command
+            .get_arg_conflicts_with(given)
+            .iter()
+            .any(|other| other.get_id() == arg.get_id())
+        || command
+            .get_arg_conflicts_with(arg)
+            .iter()
+            .any(|other| other.get_id() == given.get_id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Args, Operation};
+    use clap::{CommandFactory, Parser};
+
+    fn merged(config: &str, cli: &[&str]) -> Args {
+        let file = ConfigFile {
+            entries: toml::parse(config).unwrap(),
+        };
+        let argv = std::iter::once("mallow")
+            .chain(cli.iter().copied())
+            .map(String::from)
+            .collect();
+        let argv = file.merge_under(&Args::command(), argv).unwrap();
+        Args::try_parse_from(argv).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[test]
+    fn command_line_flags_take_precedence_over_the_file() {
+        let config = "stride = 128\noperation = \"write\"\nsimd = true\nthreads = 2\n";
+        let args = merged(config, &["--stride", "256"]);
+
+        assert_eq!(args.stride, Some(256));
+        assert!(matches!(args.operation, Operation::Write));
+        assert!(args.simd);
+        assert_eq!(args.threads, Some(2));
+    }
+
+    #[test]
+    fn command_line_flags_replace_conflicting_file_options() {
+        let args = merged("warmup = 3", &["--warmup-secs", "0.5"]);
+        assert_eq!(args.warmup_secs, Some(0.5));
+
+        let args = merged("stride = 128", &["--stride-sweep", "64:256:2"]);
+        assert_eq!(args.stride, None);
+        assert!(args.stride_sweep.is_some());
+
+        let args = merged("duration = 0.3", &["--iterations", "10"]);
+        assert_eq!(args.iterations, Some(10));
+    }
+
+    #[test]
+    fn file_options_apply_when_the_command_line_is_silent() {
+        let args = merged("warmup = 3\nduration = 0.3\nno_prefault = true", &[]);
+        assert_eq!(args.warmup, 3);
+        assert_eq!(args.duration, 0.3);
+        assert!(args.no_prefault);
+    }
+
+    #[test]
+    fn unknown_file_options_are_rejected() {
+        let file = ConfigFile {
+            entries: toml::parse("strid = 64").unwrap(),
+        };
+        let err = file
+            .merge_under(&Args::command(), vec!["mallow".into()])
+            .unwrap_err();
+        assert_eq!(err, "Unknown option in config file: strid");
+    }
+
+    #[test]
+    fn loads_toml_and_json_files() {
+        let dir = std::env::temp_dir();
+        let toml_path = dir.join(format!("mallow-config-{}.toml", std::process::id()));
+        let json_path = dir.join(format!("mallow-config-{}.json", std::process::id()));
+        std::fs::write(&toml_path, "stride = 64\nsimd = true\n").unwrap();
+        std::fs::write(&json_path, r#"{"simd": true, "stride": 64}"#).unwrap();
+
+        let from_toml = ConfigFile::load(&toml_path);
+        let from_json = ConfigFile::load(&json_path);
+        std::fs::remove_file(&toml_path).unwrap();
+        std::fs::remove_file(&json_path).unwrap();
+
+        // serde_json sorts object keys, so compare as sets
+        let mut from_json = from_json.unwrap().entries;
+        from_json.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut from_toml = from_toml.unwrap().entries;
+        from_toml.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(from_toml, from_json);
+    }
+}
@@ -0,0 +1,195 @@
+//! Parser for the subset of TOML a `--config` file needs: a single flat table of
+//! `key = value` lines whose values are strings, integers, floats or booleans. Tables, arrays
+//! and dates have no command-line equivalent and are rejected.
+
+use super::ConfigValue;
+
+/// Parses `content` into its keys and values in file order, rejecting duplicate keys
+pub fn parse(content: &str) -> Result<Vec<(String, ConfigValue)>, String> {
+    let mut entries: Vec<(String, ConfigValue)> = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let at = |e: String| format!("line {}: {}", number + 1, e);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(at(
+                "tables are not supported, options go at the top level".into()
+            ));
+        }
+
+        let (key, rest) = parse_key(line).map_err(at)?;
+        let rest = rest
+            .trim_start()
+            .strip_prefix('=')
+            .ok_or_else(|| at(format!("expected `=` after {}", key)))?;
+        let (value, rest) = parse_value(rest.trim_start()).map_err(at)?;
+        let rest = rest.trim_start();
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(at(format!("unexpected `{}` after the value", rest)));
+        }
+
+        if entries.iter().any(|(k, _)| *k == key) {
+            return Err(at(format!("{} is set more than once", key)));
+        }
+        entries.push((key, value));
+    }
+
+    Ok(entries)
+}
+
+/// Splits a bare or quoted key off the start of `line`
+fn parse_key(line: &str) -> Result<(String, &str), String> {
+    if line.starts_with('"') || line.starts_with('\'') {
+        return parse_string(line);
+    }
+    let end = line
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+        .unwrap_or(line.len());
+    if end == 0 {
+        return Err(format!("expected a key, found `{}`", line));
+    }
+    Ok((line[..end].to_string(), &line[end..]))
+}
+
+/// Splits one value off the start of `s`
+fn parse_value(s: &str) -> Result<(ConfigValue, &str), String> {
+    if s.starts_with('"') || s.starts_with('\'') {
+        let (string, rest) = parse_string(s)?;
+        return Ok((ConfigValue::String(string), rest));
+    }
+    if s.starts_with('[') || s.starts_with('{') {
+        return Err("arrays and inline tables are not supported".into());
+    }
+
+    let end = s
+        .find(|c: char| c.is_whitespace() || c == '#')
+        .unwrap_or(s.len());
+    let (token, rest) = s.split_at(end);
+    let value = match token {
+        "" => return Err("missing value".into()),
+        "true" => ConfigValue::Bool(true),
+        "false" => ConfigValue::Bool(false),
+        _ => parse_number(token)?,
+    };
+    Ok((value, rest))
+}
+
+/// Integers and floats, with TOML's `_` digit separators
+fn parse_number(token: &str) -> Result<ConfigValue, String> {
+    let digits = token.replace('_', "");
+    if let Ok(n) = digits.parse::<i64>() {
+        return Ok(ConfigValue::Integer(n));
+    }
+    // Rust accepts names like "infinity" that TOML does not, so require a digit
+    match digits.parse::<f64>() {
+        Ok(f) if digits.bytes().any(|b| b.is_ascii_digit()) => Ok(ConfigValue::Float(f)),
+        _ => Err(format!("`{}` is not a string, number or boolean", token)),
+    }
+}
+
+/// Splits a basic (`"..."`, with escapes) or literal (`'...'`) string off the start of `s`
+fn parse_string(s: &str) -> Result<(String, &str), String> {
+    let quote = s.chars().next().unwrap();
+    let body = &s[1..];
+
+    if quote == '\'' {
+        let end = body.find('\'').ok_or("unterminated string")?;
+        return Ok((body[..end].to_string(), &body[end + 1..]));
+    }
+
+    let mut out = String::new();
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((out, &body[i + 1..])),
+            '\\' => {
+                let escaped = match chars.next().map(|(_, c)| c) {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('u') => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                        u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .filter(|_| hex.len() == 4)
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("invalid escape \\u{}", hex))?
+                    }
+                    other => {
+                        return Err(format!(
+                            "invalid escape \\{}",
+                            other.map(String::from).unwrap_or_default()
+                        ))
+                    }
+                };
+                out.push(escaped);
+            }
+            _ => out.push(c),
+        }
+    }
+    Err("unterminated string".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_value_type() {
+        let entries = parse(
+            "# suite defaults\n\
+             stride = 1_024\n\
+             duration = 0.5   # seconds\n\
+             simd = true\n\
+             prefault = false\n\
+             operation = \"write\"\n\
+             'mmap-file' = '/tmp/a \"b\"'\n\
+             host = \"tab\\there \\u00e9\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                ("stride".to_string(), ConfigValue::Integer(1024)),
+                ("duration".to_string(), ConfigValue::Float(0.5)),
+                ("simd".to_string(), ConfigValue::Bool(true)),
+                ("prefault".to_string(), ConfigValue::Bool(false)),
+                ("operation".to_string(), ConfigValue::String("write".into())),
+                (
+                    "mmap-file".to_string(),
+                    ConfigValue::String("/tmp/a \"b\"".into())
+                ),
+                (
+                    "host".to_string(),
+                    ConfigValue::String("tab\there é".into())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_what_has_no_flag_equivalent() {
+        for (content, error) in [
+            ("[bench]\nstride = 64", "line 1: tables"),
+            ("threads = [1, 2]", "line 1: arrays"),
+            (
+                "stride = 64\nstride = 128",
+                "line 2: stride is set more than once",
+            ),
+            ("operation = \"write", "line 1: unterminated string"),
+            ("stride 64", "line 1: expected `=`"),
+            ("stride = 64 128", "line 1: unexpected `128`"),
+            ("duration = infinity", "line 1: `infinity` is not"),
+            ("name = \"\\q\"", "line 1: invalid escape \\q"),
+        ] {
+            let err = parse(content).unwrap_err();
+            assert!(err.starts_with(error), "{:?}: {}", content, err);
+        }
+    }
+}
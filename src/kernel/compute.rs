@@ -0,0 +1,1241 @@
+//! The compute kernels themselves, depending only on `core`. Anything random (the starting
+//! offset of the SIMD kernels, the indices of the random and chase kernels) or detected at
+//! runtime (the instruction set of `stream_read`) is passed in by the caller, so every pass
+//! here is deterministic given its arguments.
+
+use core::simd::cmp::SimdPartialOrd;
+use core::simd::Select;
+use core::simd::{f32x8, u32x8, u64x8, usizex8, Mask};
+
+/// Amount the update kernels add to every touched element
+pub const UPDATE_DELTA: u32 = 1;
+
+/// Most chains `MultiChase` walks at once; each needs a cursor register
+pub const MAX_CHAINS: usize = 64;
+
+/// Calls `$f::<N>(...)` with the const unroll factor matching the runtime `$unroll`
+macro_rules! with_unroll {
+    ($unroll:expr, $f:ident($($arg:expr),*)) => {
+        match $unroll {
+            1 => $f::<1>($($arg),*),
+            2 => $f::<2>($($arg),*),
+            4 => $f::<4>($($arg),*),
+            8 => $f::<8>($($arg),*),
+            n => panic!("unsupported unroll factor {}", n),
+        }
+    };
+}
+
+pub fn scalar_read(slice: &[u32], stride: usize, unroll: usize) -> u64 {
+    with_unroll!(unroll, scalar_read_unrolled(slice, stride))
+}
+
+fn scalar_read_unrolled<const UNROLL: usize>(slice: &[u32], stride: usize) -> u64 {
+    let mut sum = 0u64;
+    let len = slice.len();
+
+    // Process UNROLL elements per iteration
+    let main_iterations = len / (stride * UNROLL);
+    let mut i = 0;
+
+    // Main loop, fully unrolled since UNROLL is a compile-time constant
+    for _ in 0..main_iterations {
+        for j in 0..UNROLL {
+            sum = sum.wrapping_add(slice[i + stride * j] as u64);
+        }
+        i += stride * UNROLL;
+    }
+
+    // Handle remaining elements
+    while i < len {
+        sum = sum.wrapping_add(slice[i] as u64);
+        i += stride;
+    }
+
+    sum
+}
+
+/// Hints the CPU to pull the cache line holding `slice[idx]` into L1. The address may lie past
+/// the end of the slice, since prefetches never fault.
+#[inline(always)]
+fn prefetch(slice: &[u32], idx: usize) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(slice.as_ptr().wrapping_add(idx) as *const i8);
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = (slice, idx);
+}
+
+/// `scalar_read` with a software prefetch issued `distance` strided accesses ahead
+pub fn scalar_read_prefetch(slice: &[u32], stride: usize, unroll: usize, distance: usize) -> u64 {
+    with_unroll!(
+        unroll,
+        scalar_read_prefetch_unrolled(slice, stride, distance)
+    )
+}
+
+fn scalar_read_prefetch_unrolled<const UNROLL: usize>(
+    slice: &[u32],
+    stride: usize,
+    distance: usize,
+) -> u64 {
+    let mut sum = 0u64;
+    let len = slice.len();
+    let ahead = stride * distance;
+
+    // Process UNROLL elements per iteration
+    let main_iterations = len / (stride * UNROLL);
+    let mut i = 0;
+
+    // Main loop, fully unrolled since UNROLL is a compile-time constant
+    for _ in 0..main_iterations {
+        for j in 0..UNROLL {
+            prefetch(slice, i + stride * j + ahead);
+        }
+        for j in 0..UNROLL {
+            sum = sum.wrapping_add(slice[i + stride * j] as u64);
+        }
+        i += stride * UNROLL;
+    }
+
+    // Handle remaining elements
+    while i < len {
+        prefetch(slice, i + ahead);
+        sum = sum.wrapping_add(slice[i] as u64);
+        i += stride;
+    }
+
+    sum
+}
+
+/// Sums every `stride`-th element starting at `offset`, eight per gather. `offset` must be
+/// below `stride` (0 for unit stride), as the SIMD kernels' bounds checks assume.
+pub fn simd_read(slice: &[u32], stride: usize, offset: usize) -> u64 {
+    let mut sum: u64 = 0;
+
+    // Unit stride reads every element, which contiguous loads do far faster than a gather
+    if stride == 1 {
+        let (chunks, tail) = slice.as_chunks::<8>();
+        for chunk in chunks {
+            sum += u32x8::from_array(*chunk).horizontal_sum();
+        }
+        return sum + u32x8::load_or_default(tail).horizontal_sum();
+    }
+
+    // Create indices for gather: [0*stride, 1*stride, 2*stride, ..., 15*stride]
+    let indices = usizex8::from_array(core::array::from_fn(|i| i * stride + offset));
+
+    // Process strided elements in chunks
+    let mut base = 0;
+    while base + (7 * stride) < slice.len() {
+        // Gather values from strided locations
+        sum += u32x8::gather_or_default(&slice[base..], indices).horizontal_sum();
+
+        base += stride * 8;
+    }
+
+    // Gather the final partial vector with only the in-bounds lanes enabled
+    if base < slice.len() {
+        let tail = &slice[base..];
+        let enable = indices.simd_lt(usizex8::splat(tail.len()));
+        sum += u32x8::gather_select(tail, enable, indices, u32x8::splat(0)).horizontal_sum();
+    }
+
+    sum
+}
+
+/// `simd_read` over 64-bit elements. Eight lanes span twice as many bytes, so `stride` is in
+/// u64 elements and the last full vector ends at `base + 7 * stride`.
+pub fn simd_read_u64(slice: &[u64], stride: usize, offset: usize) -> u64 {
+    let mut sum = 0u64;
+
+    if stride == 1 {
+        let (chunks, tail) = slice.as_chunks::<8>();
+        for chunk in chunks {
+            sum = sum.wrapping_add(u64x8::from_array(*chunk).horizontal_sum());
+        }
+        return sum.wrapping_add(u64x8::load_or_default(tail).horizontal_sum());
+    }
+
+    let indices = usizex8::from_array(core::array::from_fn(|i| i * stride + offset));
+
+    let mut base = 0;
+    while base + (7 * stride) < slice.len() {
+        sum = sum.wrapping_add(u64x8::gather_or_default(&slice[base..], indices).horizontal_sum());
+        base += stride * 8;
+    }
+
+    // Gather the final partial vector with only the in-bounds lanes enabled
+    if base < slice.len() {
+        let tail = &slice[base..];
+        let enable = indices.simd_lt(usizex8::splat(tail.len()));
+        sum = sum.wrapping_add(
+            u64x8::gather_select(tail, enable, indices, u64x8::splat(0)).horizontal_sum(),
+        );
+    }
+
+    sum
+}
+
+/// `simd_read` over f32 elements. Each vector's lanes are summed in f32 and accumulated in
+/// f64. Float addition is not associative, so the result differs from a scalar sum in the last
+/// bits and must be compared with a tolerance.
+pub fn simd_read_f32(slice: &[f32], stride: usize, offset: usize) -> f64 {
+    let mut sum = 0.0f64;
+
+    if stride == 1 {
+        let (chunks, tail) = slice.as_chunks::<8>();
+        for chunk in chunks {
+            sum += f32x8::from_array(*chunk).horizontal_sum() as f64;
+        }
+        return sum + f32x8::load_or_default(tail).horizontal_sum() as f64;
+    }
+
+    let indices = usizex8::from_array(core::array::from_fn(|i| i * stride + offset));
+
+    let mut base = 0;
+    while base + (7 * stride) < slice.len() {
+        sum += f32x8::gather_or_default(&slice[base..], indices).horizontal_sum() as f64;
+        base += stride * 8;
+    }
+
+    // Gather the final partial vector with only the in-bounds lanes enabled
+    if base < slice.len() {
+        let tail = &slice[base..];
+        let enable = indices.simd_lt(usizex8::splat(tail.len()));
+        sum +=
+            f32x8::gather_select(tail, enable, indices, f32x8::splat(0.0)).horizontal_sum() as f64;
+    }
+
+    sum
+}
+
+/// `simd_write` over f32 elements, returning the f64 sum of the values written
+pub fn simd_write_f32(slice: &mut [f32], stride: usize, offset: usize, safe: bool) -> f64 {
+    let mut sum = 0.0f64;
+
+    if stride == 1 {
+        let values_at =
+            |base: usize| f32x8::from_array(core::array::from_fn(|j| (base + j) as f32 * 0.5));
+        let (chunks, tail) = slice.as_chunks_mut::<8>();
+        for (i, chunk) in chunks.iter_mut().enumerate() {
+            let values = values_at(i * 8);
+            *chunk = values.to_array();
+            sum += values.horizontal_sum() as f64;
+        }
+        let enable = tail_mask(tail.len());
+        let values = enable
+            .cast::<i32>()
+            .select(values_at(chunks.len() * 8), f32x8::splat(0.0));
+        values.store_select(tail, enable.cast());
+        return sum + values.horizontal_sum() as f64;
+    }
+
+    let indices = usizex8::from_array(core::array::from_fn(|i| i * stride + offset));
+    let values_at =
+        |base: usize| f32x8::from_array(core::array::from_fn(|i| (base + i * stride) as f32 * 0.5));
+
+    // The last lane of a vector lands on base + 7 * stride + offset
+    let mut base = 0;
+    while base + 7 * stride + offset < slice.len() {
+        let values = values_at(base);
+
+        if safe {
+            values.scatter_checked(&mut slice[base..], indices);
+        } else {
+            // SAFETY: the loop bound keeps base + 7 * stride + offset within the slice
+            unsafe { values.scatter_unchecked(&mut slice[base..], indices) };
+        }
+
+        sum += values.horizontal_sum() as f64;
+        base += stride * 8;
+    }
+
+    // Scatter the final partial vector with only the in-bounds lanes enabled
+    if base + offset < slice.len() {
+        let tail = &mut slice[base..];
+        let enable = indices.simd_lt(usizex8::splat(tail.len()));
+        let values = enable
+            .cast::<i32>()
+            .select(values_at(base), f32x8::splat(0.0));
+        values.scatter_select(tail, enable, indices);
+        sum += values.horizontal_sum() as f64;
+    }
+
+    sum
+}
+
+/// Streaming-load instruction sets available to `stream_read`. The caller detects them once
+/// (see `detect_stream_isa`) instead of every pass checking the CPU again. The default has
+/// none, so `stream_read` reads through `scalar_read`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StreamIsa {
+    avx2: bool,
+    sse41: bool,
+}
+
+impl StreamIsa {
+    /// # Safety
+    /// The CPU running `stream_read` must support every instruction set enabled here.
+    pub const unsafe fn new_unchecked(avx2: bool, sse41: bool) -> Self {
+        StreamIsa { avx2, sse41 }
+    }
+}
+
+/// Strided read through non-temporal streaming loads (MOVNTDQA), which can read
+/// write-combining memory and limit cache pollution. Each access loads the aligned vector that
+/// holds `slice[i]` and sums all of its lanes. Falls back to `scalar_read` when `isa` has
+/// neither AVX2 nor SSE4.1 or the buffer is not aligned to the vector width.
+pub fn stream_read(slice: &[u32], stride: usize, unroll: usize, isa: StreamIsa) -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let addr = slice.as_ptr() as usize;
+        if isa.avx2 && addr.is_multiple_of(32) {
+            // SAFETY: StreamIsa guarantees AVX2, and the slice start is 32-byte aligned
+            return unsafe { stream_read_avx2(slice, stride) };
+        }
+        if isa.sse41 && addr.is_multiple_of(16) {
+            // SAFETY: StreamIsa guarantees SSE4.1, and the slice start is 16-byte aligned
+            return unsafe { stream_read_sse41(slice, stride) };
+        }
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = isa;
+    scalar_read(slice, stride, unroll)
+}
+
+/// # Safety
+/// Requires AVX2 and a 32-byte aligned `slice`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn stream_read_avx2(slice: &[u32], stride: usize) -> u64 {
+    use core::arch::x86_64::{
+        __m256i, _mm256_add_epi32, _mm256_setzero_si256, _mm256_storeu_si256,
+        _mm256_stream_load_si256, _mm_mfence,
+    };
+    const LANES: usize = 8;
+
+    let mut acc = _mm256_setzero_si256();
+    let mut i = 0;
+    // Stop at the first access whose vector would run past the end of the slice
+    while i < slice.len() {
+        let base = i & !(LANES - 1);
+        if base + LANES > slice.len() {
+            break;
+        }
+        // SAFETY: base is a multiple of LANES from an aligned start, and the vector is in bounds
+        let v = unsafe { _mm256_stream_load_si256(slice.as_ptr().add(base) as *const __m256i) };
+        acc = _mm256_add_epi32(acc, v);
+        i += stride;
+    }
+
+    let mut sum = 0u64;
+    while i < slice.len() {
+        sum = sum.wrapping_add(slice[i] as u64);
+        i += stride;
+    }
+
+    // Streaming loads are weakly ordered, so fence before anything relies on what was read
+    _mm_mfence();
+
+    let mut lanes = [0u32; LANES];
+    // SAFETY: lanes is exactly 32 bytes
+    unsafe { _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, acc) };
+    lanes
+        .iter()
+        .fold(sum, |acc, &lane| acc.wrapping_add(lane as u64))
+}
+
+/// # Safety
+/// Requires SSE4.1 and a 16-byte aligned `slice`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+unsafe fn stream_read_sse41(slice: &[u32], stride: usize) -> u64 {
+    use core::arch::x86_64::{
+        __m128i, _mm_add_epi32, _mm_mfence, _mm_setzero_si128, _mm_storeu_si128,
+        _mm_stream_load_si128,
+    };
+    const LANES: usize = 4;
+
+    let mut acc = _mm_setzero_si128();
+    let mut i = 0;
+    // Stop at the first access whose vector would run past the end of the slice
+    while i < slice.len() {
+        let base = i & !(LANES - 1);
+        if base + LANES > slice.len() {
+            break;
+        }
+        // SAFETY: base is a multiple of LANES from an aligned start, and the vector is in bounds
+        let v = unsafe { _mm_stream_load_si128(slice.as_ptr().add(base) as *const __m128i) };
+        acc = _mm_add_epi32(acc, v);
+        i += stride;
+    }
+
+    let mut sum = 0u64;
+    while i < slice.len() {
+        sum = sum.wrapping_add(slice[i] as u64);
+        i += stride;
+    }
+
+    // Streaming loads are weakly ordered, so fence before anything relies on what was read
+    _mm_mfence();
+
+    let mut lanes = [0u32; LANES];
+    // SAFETY: lanes is exactly 16 bytes
+    unsafe { _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, acc) };
+    lanes
+        .iter()
+        .fold(sum, |acc, &lane| acc.wrapping_add(lane as u64))
+}
+
+/// Follows each chain from `heads` for `steps` links, advancing all chains one link per round
+/// so their misses overlap. Each load depends on the previous load of the same chain only.
+pub fn multi_chase(slice: &[u32], heads: &[u32], steps: usize) -> u64 {
+    let mut sum = 0u64;
+    let mut cursors = [0u32; MAX_CHAINS];
+    let cursors = &mut cursors[..heads.len()];
+    cursors.copy_from_slice(heads);
+
+    for _ in 0..steps {
+        for cursor in cursors.iter_mut() {
+            *cursor = slice[*cursor as usize];
+            sum = sum.wrapping_add(*cursor as u64);
+        }
+    }
+
+    sum
+}
+
+pub fn random_read(slice: &[u32], indices: &[u32]) -> u64 {
+    let mut sum = 0u64;
+
+    // Indices are independent, so the loads can be in flight concurrently
+    for &idx in indices {
+        sum = sum.wrapping_add(slice[idx as usize] as u64);
+    }
+
+    sum
+}
+
+/// Sums `block_len` contiguous elements at every `stride`-th position of `slice`, a gather of
+/// sparse block starts each followed by a short stream. The last block stops at the end.
+pub fn block_read(slice: &[u32], stride: usize, block_len: usize) -> u64 {
+    let mut sum = 0u64;
+
+    for start in (0..slice.len()).step_by(stride) {
+        let end = (start + block_len).min(slice.len());
+        for &val in &slice[start..end] {
+            sum = sum.wrapping_add(val as u64);
+        }
+    }
+
+    sum
+}
+
+/// Sums every element of `slice` viewed as rows of `row_len` elements, visiting it in
+/// `tile` x `tile` blocks. A trailing partial row or tile is walked like any other.
+pub fn tiled_2d(slice: &[u32], row_len: usize, tile: usize) -> u64 {
+    let mut sum = 0u64;
+    let rows = slice.len().div_ceil(row_len);
+
+    for tile_row in (0..rows).step_by(tile) {
+        for tile_col in (0..row_len).step_by(tile) {
+            for row in tile_row..(tile_row + tile).min(rows) {
+                let row_start = row * row_len;
+                let start = (row_start + tile_col).min(slice.len());
+                let end = (row_start + tile_col + tile)
+                    .min(row_start + row_len)
+                    .min(slice.len());
+                for &val in &slice[start..end] {
+                    sum = sum.wrapping_add(val as u64);
+                }
+            }
+        }
+    }
+
+    sum
+}
+
+pub fn scalar_write(slice: &mut [u32], stride: usize, unroll: usize) -> u64 {
+    with_unroll!(unroll, scalar_write_unrolled(slice, stride))
+}
+
+fn scalar_write_unrolled<const UNROLL: usize>(slice: &mut [u32], stride: usize) -> u64 {
+    let mut sum = 0u64;
+    let len = slice.len();
+
+    // Process UNROLL elements per iteration
+    let main_iterations = len / (stride * UNROLL);
+    let mut i = 0;
+
+    // Main loop, fully unrolled since UNROLL is a compile-time constant
+    for _ in 0..main_iterations {
+        // Write and accumulate values
+        for j in 0..UNROLL {
+            let idx = i + stride * j;
+            let val = (idx as u32).wrapping_mul(7); // Some deterministic value
+            slice[idx] = val;
+            sum = sum.wrapping_add(val as u64);
+        }
+        i += stride * UNROLL;
+    }
+
+    // Handle remaining elements
+    while i < len {
+        let val = (i as u32).wrapping_mul(7);
+        slice[i] = val;
+        sum = sum.wrapping_add(val as u64);
+        i += stride;
+    }
+
+    sum
+}
+
+/// Scatters a computed value to every `stride`-th element starting at `offset`, with `offset`
+/// below `stride` as for `simd_read`
+pub fn simd_write(slice: &mut [u32], stride: usize, offset: usize, safe: bool) -> u64 {
+    let mut sum: u64 = 0;
+
+    // Unit stride writes every element, which contiguous stores do far faster than a scatter.
+    // Like the strided loop, the final partial vector is written with a mask.
+    if stride == 1 {
+        let values_at = |base: usize| {
+            u32x8::from_array(core::array::from_fn(|j| {
+                ((base + j) as u32).wrapping_mul(7)
+            }))
+        };
+        let (chunks, tail) = slice.as_chunks_mut::<8>();
+        for (i, chunk) in chunks.iter_mut().enumerate() {
+            let values = values_at(i * 8);
+            *chunk = values.to_array();
+            sum += values.horizontal_sum();
+        }
+        let enable = tail_mask(tail.len());
+        let values = enable
+            .cast::<i32>()
+            .select(values_at(chunks.len() * 8), u32x8::splat(0));
+        values.store_select(tail, enable.cast());
+        return sum + values.horizontal_sum();
+    }
+
+    // Create indices for scatter: [0*stride, 1*stride, 2*stride, ..., 7*stride]
+    let indices = usizex8::from_array(core::array::from_fn(|i| i * stride + offset));
+
+    // Create values to write: [i*7, (i+1)*7, ..., (i+7)*7]
+    let values_at = |base: usize| {
+        u32x8::from_array(core::array::from_fn(|i| {
+            ((base + i * stride) as u32).wrapping_mul(7)
+        }))
+    };
+
+    // The last lane of a vector lands on base + 7 * stride + offset
+    let mut base = 0;
+    while base + 7 * stride + offset < slice.len() {
+        let values = values_at(base);
+
+        // Scatter values to strided locations
+        if safe {
+            values.scatter_checked(&mut slice[base..], indices);
+        } else {
+            // SAFETY: the loop bound keeps base + 7 * stride + offset within the slice
+            unsafe { values.scatter_unchecked(&mut slice[base..], indices) };
+        }
+
+        sum += values.horizontal_sum();
+        base += stride * 8;
+    }
+
+    // Scatter the final partial vector with only the in-bounds lanes enabled
+    if base + offset < slice.len() {
+        let tail = &mut slice[base..];
+        let enable = indices.simd_lt(usizex8::splat(tail.len()));
+        let values = enable
+            .cast::<i32>()
+            .select(values_at(base), u32x8::splat(0));
+        values.scatter_select(tail, enable, indices);
+        sum += values.horizontal_sum();
+    }
+
+    sum
+}
+
+/// `simd_write` over 64-bit elements, with `stride` in u64 elements
+pub fn simd_write_u64(slice: &mut [u64], stride: usize, offset: usize, safe: bool) -> u64 {
+    let mut sum: u64 = 0;
+
+    if stride == 1 {
+        let values_at = |base: usize| {
+            u64x8::from_array(core::array::from_fn(|j| {
+                ((base + j) as u64).wrapping_mul(7)
+            }))
+        };
+        let (chunks, tail) = slice.as_chunks_mut::<8>();
+        for (i, chunk) in chunks.iter_mut().enumerate() {
+            let values = values_at(i * 8);
+            *chunk = values.to_array();
+            sum = sum.wrapping_add(values.horizontal_sum());
+        }
+        let enable = tail_mask(tail.len());
+        let values = enable
+            .cast::<i64>()
+            .select(values_at(chunks.len() * 8), u64x8::splat(0));
+        values.store_select(tail, enable.cast());
+        return sum.wrapping_add(values.horizontal_sum());
+    }
+
+    let indices = usizex8::from_array(core::array::from_fn(|i| i * stride + offset));
+    let values_at = |base: usize| {
+        u64x8::from_array(core::array::from_fn(|i| {
+            ((base + i * stride) as u64).wrapping_mul(7)
+        }))
+    };
+
+    // The last lane of a vector lands on base + 7 * stride + offset
+    let mut base = 0;
+    while base + 7 * stride + offset < slice.len() {
+        let values = values_at(base);
+
+        if safe {
+            values.scatter_checked(&mut slice[base..], indices);
+        } else {
+            // SAFETY: the loop bound keeps base + 7 * stride + offset within the slice
+            unsafe { values.scatter_unchecked(&mut slice[base..], indices) };
+        }
+
+        sum = sum.wrapping_add(values.horizontal_sum());
+        base += stride * 8;
+    }
+
+    // Scatter the final partial vector with only the in-bounds lanes enabled
+    if base + offset < slice.len() {
+        let tail = &mut slice[base..];
+        let enable = indices.simd_lt(usizex8::splat(tail.len()));
+        let values = enable
+            .cast::<i64>()
+            .select(values_at(base), u64x8::splat(0));
+        values.scatter_select(tail, enable, indices);
+        sum = sum.wrapping_add(values.horizontal_sum());
+    }
+
+    sum
+}
+
+pub fn scalar_update(slice: &mut [u32], stride: usize, unroll: usize) -> u64 {
+    with_unroll!(unroll, scalar_update_unrolled(slice, stride))
+}
+
+fn scalar_update_unrolled<const UNROLL: usize>(slice: &mut [u32], stride: usize) -> u64 {
+    let mut sum = 0u64;
+    let len = slice.len();
+
+    // Process UNROLL elements per iteration
+    let main_iterations = len / (stride * UNROLL);
+    let mut i = 0;
+
+    // Main loop, fully unrolled since UNROLL is a compile-time constant
+    for _ in 0..main_iterations {
+        for j in 0..UNROLL {
+            let idx = i + stride * j;
+            let val = slice[idx].wrapping_add(UPDATE_DELTA);
+            slice[idx] = val;
+            sum = sum.wrapping_add(val as u64);
+        }
+        i += stride * UNROLL;
+    }
+
+    // Handle remaining elements
+    while i < len {
+        let val = slice[i].wrapping_add(UPDATE_DELTA);
+        slice[i] = val;
+        sum = sum.wrapping_add(val as u64);
+        i += stride;
+    }
+
+    sum
+}
+
+/// Adds `UPDATE_DELTA` to every `stride`-th element starting at `offset`, with `offset` below
+/// `stride` as for `simd_read`
+pub fn simd_update(slice: &mut [u32], stride: usize, offset: usize, safe: bool) -> u64 {
+    let mut sum: u64 = 0;
+
+    // Unit stride updates every element with contiguous loads and stores, masking the final
+    // partial vector
+    if stride == 1 {
+        let (chunks, tail) = slice.as_chunks_mut::<8>();
+        for chunk in chunks {
+            let values = u32x8::from_array(*chunk) + u32x8::splat(UPDATE_DELTA);
+            *chunk = values.to_array();
+            sum += values.horizontal_sum();
+        }
+        let enable = tail_mask(tail.len());
+        let values = u32x8::load_or_default(tail) + u32x8::splat(UPDATE_DELTA);
+        let values = enable.cast::<i32>().select(values, u32x8::splat(0));
+        values.store_select(tail, enable.cast());
+        return sum + values.horizontal_sum();
+    }
+
+    // Create indices for gather/scatter: [0*stride, 1*stride, 2*stride, ..., 7*stride]
+    let indices = usizex8::from_array(core::array::from_fn(|i| i * stride + offset));
+    let delta = u32x8::splat(UPDATE_DELTA);
+
+    // The last lane of a vector lands on base + 7 * stride + offset
+    let mut base = 0;
+    while base + 7 * stride + offset < slice.len() {
+        // Gather, add and scatter back to the same strided locations
+        let values = u32x8::gather_or_default(&slice[base..], indices) + delta;
+        if safe {
+            values.scatter_checked(&mut slice[base..], indices);
+        } else {
+            // SAFETY: the loop bound keeps base + 7 * stride + offset within the slice
+            unsafe { values.scatter_unchecked(&mut slice[base..], indices) };
+        }
+
+        sum += values.horizontal_sum();
+        base += stride * 8;
+    }
+
+    // Update the final partial vector with only the in-bounds lanes enabled
+    if base + offset < slice.len() {
+        let tail = &mut slice[base..];
+        let enable = indices.simd_lt(usizex8::splat(tail.len()));
+        let values = u32x8::gather_select(tail, enable, indices, u32x8::splat(0)) + delta;
+        let values = enable.cast::<i32>().select(values, u32x8::splat(0));
+        values.scatter_select(tail, enable, indices);
+        sum += values.horizontal_sum();
+    }
+
+    sum
+}
+
+pub fn copy(src: &[u32], dst: &mut [u32], stride: usize, unroll: usize) -> u64 {
+    with_unroll!(unroll, copy_unrolled(src, dst, stride))
+}
+
+fn copy_unrolled<const UNROLL: usize>(src: &[u32], dst: &mut [u32], stride: usize) -> u64 {
+    let mut sum = 0u64;
+    let len = src.len().min(dst.len());
+
+    // Process UNROLL elements per iteration
+    let main_iterations = len / (stride * UNROLL);
+    let mut i = 0;
+
+    // Main loop, fully unrolled since UNROLL is a compile-time constant
+    for _ in 0..main_iterations {
+        for j in 0..UNROLL {
+            let idx = i + stride * j;
+            let val = src[idx];
+            dst[idx] = val;
+            sum = sum.wrapping_add(val as u64);
+        }
+        i += stride * UNROLL;
+    }
+
+    // Handle remaining elements
+    while i < len {
+        let val = src[i];
+        dst[i] = val;
+        sum = sum.wrapping_add(val as u64);
+        i += stride;
+    }
+
+    sum
+}
+
+/// Reads `src` and writes `dst` at the same strided positions, without the written values
+/// depending on the reads. Unlike `copy`, the stores never wait on a load, so the memory
+/// controller sees a read stream and a write stream concurrently.
+pub fn bidir(src: &[u32], dst: &mut [u32], stride: usize, unroll: usize) -> u64 {
+    with_unroll!(unroll, bidir_unrolled(src, dst, stride))
+}
+
+fn bidir_unrolled<const UNROLL: usize>(src: &[u32], dst: &mut [u32], stride: usize) -> u64 {
+    let mut sum = 0u64;
+    let len = src.len().min(dst.len());
+
+    // Process UNROLL elements per iteration
+    let main_iterations = len / (stride * UNROLL);
+    let mut i = 0;
+
+    // Main loop, fully unrolled since UNROLL is a compile-time constant
+    for _ in 0..main_iterations {
+        for j in 0..UNROLL {
+            let idx = i + stride * j;
+            let val = (idx as u32).wrapping_mul(7);
+            dst[idx] = val;
+            sum = sum.wrapping_add(src[idx] as u64).wrapping_add(val as u64);
+        }
+        i += stride * UNROLL;
+    }
+
+    // Handle remaining elements
+    while i < len {
+        let val = (i as u32).wrapping_mul(7);
+        dst[i] = val;
+        sum = sum.wrapping_add(src[i] as u64).wrapping_add(val as u64);
+        i += stride;
+    }
+
+    sum
+}
+
+/// Enables the first `len` of eight lanes, for the partial vector at the end of a contiguous
+/// slice
+fn tail_mask(len: usize) -> Mask<isize, 8> {
+    usizex8::from_array(core::array::from_fn(|j| j)).simd_lt(usizex8::splat(len))
+}
+
+// Add horizontal_sum for u32x8, u64x8 and f32x8
+trait SimdExt {
+    type Scalar;
+    fn horizontal_sum(self) -> Self::Scalar;
+}
+
+// u32 lanes are summed in u64, as the scalar kernels accumulate them, so checksums agree
+impl SimdExt for u32x8 {
+    type Scalar = u64;
+    fn horizontal_sum(self) -> u64 {
+        let arr = self.to_array();
+        arr.iter().fold(0u64, |acc, &x| acc + x as u64)
+    }
+}
+
+impl SimdExt for u64x8 {
+    type Scalar = u64;
+    fn horizontal_sum(self) -> u64 {
+        let arr = self.to_array();
+        arr.iter().fold(0u64, |acc, &x| acc.wrapping_add(x))
+    }
+}
+
+impl SimdExt for f32x8 {
+    type Scalar = f32;
+    fn horizontal_sum(self) -> f32 {
+        self.to_array().iter().sum()
+    }
+}
+
+// Add scatter support for u32x8, u64x8 and f32x8
+trait SimdScatter {
+    type Scalar;
+
+    /// Writes lane `i` to `slice[indices[i]]` without bounds checks. Debug builds still assert
+    /// every index is in bounds.
+    ///
+    /// # Safety
+    /// Every index must be less than `slice.len()`.
+    unsafe fn scatter_unchecked(self, slice: &mut [Self::Scalar], indices: usizex8);
+
+    /// Bounds-checked scatter for `--safe`, panicking on an out-of-bounds index
+    fn scatter_checked(self, slice: &mut [Self::Scalar], indices: usizex8);
+}
+
+impl SimdScatter for u32x8 {
+    type Scalar = u32;
+    unsafe fn scatter_unchecked(self, slice: &mut [u32], indices: usizex8) {
+        let values = self.to_array();
+        let idx = indices.to_array();
+        for i in 0..8 {
+            debug_assert!(
+                idx[i] < slice.len(),
+                "scatter index {} out of bounds for length {}",
+                idx[i],
+                slice.len()
+            );
+            *slice.get_unchecked_mut(idx[i]) = values[i];
+        }
+    }
+
+    fn scatter_checked(self, slice: &mut [u32], indices: usizex8) {
+        for (value, idx) in self.to_array().into_iter().zip(indices.to_array()) {
+            slice[idx] = value;
+        }
+    }
+}
+
+impl SimdScatter for u64x8 {
+    type Scalar = u64;
+    unsafe fn scatter_unchecked(self, slice: &mut [u64], indices: usizex8) {
+        let values = self.to_array();
+        let idx = indices.to_array();
+        for i in 0..8 {
+            debug_assert!(
+                idx[i] < slice.len(),
+                "scatter index {} out of bounds for length {}",
+                idx[i],
+                slice.len()
+            );
+            *slice.get_unchecked_mut(idx[i]) = values[i];
+        }
+    }
+
+    fn scatter_checked(self, slice: &mut [u64], indices: usizex8) {
+        for (value, idx) in self.to_array().into_iter().zip(indices.to_array()) {
+            slice[idx] = value;
+        }
+    }
+}
+
+impl SimdScatter for f32x8 {
+    type Scalar = f32;
+    unsafe fn scatter_unchecked(self, slice: &mut [f32], indices: usizex8) {
+        let values = self.to_array();
+        let idx = indices.to_array();
+        for i in 0..8 {
+            debug_assert!(
+                idx[i] < slice.len(),
+                "scatter index {} out of bounds for length {}",
+                idx[i],
+                slice.len()
+            );
+            *slice.get_unchecked_mut(idx[i]) = values[i];
+        }
+    }
+
+    fn scatter_checked(self, slice: &mut [f32], indices: usizex8) {
+        for (value, idx) in self.to_array().into_iter().zip(indices.to_array()) {
+            slice[idx] = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Buffer lengths around vector and stride multiples, including ones shorter than a vector
+    const LENGTHS: [usize; 10] = [1, 7, 8, 9, 63, 64, 65, 255, 256, 1001];
+    /// Strides of two and up, with odd ones that leave a different tail for every length
+    const STRIDES: [usize; 7] = [2, 3, 5, 8, 9, 36, 144];
+
+    /// Positions a strided kernel starting at `offset` visits
+    fn visited(len: usize, stride: usize, offset: usize) -> impl Iterator<Item = usize> {
+        (offset..len).step_by(stride)
+    }
+
+    #[test]
+    fn simd_write_stays_in_bounds_at_every_offset() {
+        for len in LENGTHS {
+            for stride in STRIDES {
+                for offset in 1..stride {
+                    for safe in [false, true] {
+                        let mut slice = vec![u32::MAX; len];
+                        let sum = simd_write(&mut slice, stride, offset, safe);
+
+                        let mut expected = vec![u32::MAX; len];
+                        let mut expected_sum = 0u64;
+                        for idx in visited(len, stride, offset) {
+                            expected[idx] = ((idx - offset) as u32).wrapping_mul(7);
+                            expected_sum += expected[idx] as u64;
+                        }
+                        assert_eq!(slice, expected, "len {len} stride {stride} offset {offset}");
+                        assert_eq!(
+                            sum, expected_sum,
+                            "len {len} stride {stride} offset {offset}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn simd_write_u64_stays_in_bounds_at_every_offset() {
+        for len in LENGTHS {
+            for stride in STRIDES {
+                for offset in 1..stride {
+                    for safe in [false, true] {
+                        let mut slice = vec![u64::MAX; len];
+                        let sum = simd_write_u64(&mut slice, stride, offset, safe);
+
+                        let mut expected = vec![u64::MAX; len];
+                        let mut expected_sum = 0u64;
+                        for idx in visited(len, stride, offset) {
+                            expected[idx] = ((idx - offset) as u64).wrapping_mul(7);
+                            expected_sum = expected_sum.wrapping_add(expected[idx]);
+                        }
+                        assert_eq!(slice, expected, "len {len} stride {stride} offset {offset}");
+                        assert_eq!(
+                            sum, expected_sum,
+                            "len {len} stride {stride} offset {offset}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn simd_read_u64_gathers_every_strided_element() {
+        for len in LENGTHS {
+            // Values past u32 range catch a gather that truncates to 32 bits
+            let slice: Vec<u64> = (0..len as u64).map(|i| (i << 32) | i).collect();
+            for stride in STRIDES {
+                for offset in 0..stride {
+                    let expected = visited(len, stride, offset)
+                        .fold(0u64, |acc, idx| acc.wrapping_add(slice[idx]));
+                    assert_eq!(
+                        simd_read_u64(&slice, stride, offset),
+                        expected,
+                        "len {len} stride {stride} offset {offset}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Float sums depend on the order of additions, so compare them relative to their size
+    fn assert_close(actual: f64, expected: f64, context: &str) {
+        let tolerance = 1e-5 * expected.abs().max(1.0);
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{context}: {actual} differs from {expected} by more than {tolerance}"
+        );
+    }
+
+    #[test]
+    fn simd_read_f32_sums_within_tolerance() {
+        for len in LENGTHS {
+            let slice: Vec<f32> = (0..len).map(|i| i as f32 * 0.37 + 0.1).collect();
+            for stride in [1].into_iter().chain(STRIDES) {
+                for offset in 0..stride {
+                    let expected: f64 = visited(len, stride, offset)
+                        .map(|idx| slice[idx] as f64)
+                        .sum();
+                    assert_close(
+                        simd_read_f32(&slice, stride, offset),
+                        expected,
+                        &format!("len {len} stride {stride} offset {offset}"),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn simd_write_f32_stays_in_bounds_at_every_offset() {
+        for len in LENGTHS {
+            for stride in STRIDES {
+                for offset in 1..stride {
+                    for safe in [false, true] {
+                        let mut slice = vec![-1.0f32; len];
+                        let sum = simd_write_f32(&mut slice, stride, offset, safe);
+
+                        let mut expected = vec![-1.0f32; len];
+                        for idx in visited(len, stride, offset) {
+                            expected[idx] = (idx - offset) as f32 * 0.5;
+                        }
+                        let context = format!("len {len} stride {stride} offset {offset}");
+                        assert_eq!(slice, expected, "{context}");
+                        let expected_sum = visited(len, stride, offset)
+                            .map(|idx| expected[idx] as f64)
+                            .sum();
+                        assert_close(sum, expected_sum, &context);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn simd_update_stays_in_bounds_at_every_offset() {
+        for len in LENGTHS {
+            for stride in STRIDES {
+                for offset in 1..stride {
+                    for safe in [false, true] {
+                        let mut slice: Vec<u32> = (0..len as u32).collect();
+                        let sum = simd_update(&mut slice, stride, offset, safe);
+
+                        let mut expected: Vec<u32> = (0..len as u32).collect();
+                        let mut expected_sum = 0u64;
+                        for idx in visited(len, stride, offset) {
+                            expected[idx] += UPDATE_DELTA;
+                            expected_sum += expected[idx] as u64;
+                        }
+                        assert_eq!(slice, expected, "len {len} stride {stride} offset {offset}");
+                        assert_eq!(
+                            sum, expected_sum,
+                            "len {len} stride {stride} offset {offset}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unit_stride_simd_reads_match_scalar_reads() {
+        for len in LENGTHS {
+            let slice: Vec<u32> = (0..len as u32)
+                .map(|i| i.wrapping_mul(2654435761))
+                .collect();
+            assert_eq!(
+                simd_read(&slice, 1, 0),
+                scalar_read(&slice, 1, 1),
+                "len {len}"
+            );
+
+            let wide: Vec<u64> = (0..len as u64).map(|i| (i << 40) | i).collect();
+            let expected = wide.iter().fold(0u64, |acc, &x| acc.wrapping_add(x));
+            assert_eq!(simd_read_u64(&wide, 1, 0), expected, "len {len}");
+        }
+    }
+
+    #[test]
+    fn unit_stride_simd_writes_match_scalar_writes() {
+        for len in LENGTHS {
+            let mut simd = vec![u32::MAX; len];
+            let mut scalar = vec![u32::MAX; len];
+            let sum = simd_write(&mut simd, 1, 0, false);
+            assert_eq!(sum, scalar_write(&mut scalar, 1, 1), "len {len}");
+            assert_eq!(simd, scalar, "len {len}");
+
+            let mut wide = vec![u64::MAX; len];
+            let sum = simd_write_u64(&mut wide, 1, 0, false);
+            let expected: Vec<u64> = (0..len as u64).map(|i| i.wrapping_mul(7)).collect();
+            assert_eq!(wide, expected, "len {len}");
+            assert_eq!(sum, expected.iter().sum::<u64>(), "len {len}");
+
+            let mut floats = vec![-1.0f32; len];
+            let sum = simd_write_f32(&mut floats, 1, 0, false);
+            let expected: Vec<f32> = (0..len).map(|i| i as f32 * 0.5).collect();
+            assert_eq!(floats, expected, "len {len}");
+            let expected_sum = expected.iter().map(|&x| x as f64).sum();
+            assert_close(sum, expected_sum, &format!("len {len}"));
+        }
+    }
+
+    #[test]
+    fn unit_stride_simd_update_matches_scalar_update() {
+        for len in LENGTHS {
+            let mut simd: Vec<u32> = (0..len as u32).collect();
+            let mut scalar = simd.clone();
+            let sum = simd_update(&mut simd, 1, 0, false);
+            assert_eq!(sum, scalar_update(&mut scalar, 1, 1), "len {len}");
+            assert_eq!(simd, scalar, "len {len}");
+        }
+    }
+
+    #[test]
+    fn every_unroll_factor_matches_no_unrolling() {
+        for len in LENGTHS {
+            let src: Vec<u32> = (0..len as u32)
+                .map(|i| i.wrapping_mul(2654435761))
+                .collect();
+            for stride in [1].into_iter().chain(STRIDES) {
+                let context = format!("len {len} stride {stride}");
+                let write = |unroll| {
+                    let mut slice = vec![u32::MAX; len];
+                    (scalar_write(&mut slice, stride, unroll), slice)
+                };
+                let update = |unroll| {
+                    let mut slice = src.clone();
+                    (scalar_update(&mut slice, stride, unroll), slice)
+                };
+                let copied = |unroll| {
+                    let mut dst = vec![u32::MAX; len];
+                    (copy(&src, &mut dst, stride, unroll), dst)
+                };
+                let both = |unroll| {
+                    let mut dst = vec![u32::MAX; len];
+                    (bidir(&src, &mut dst, stride, unroll), dst)
+                };
+
+                for unroll in crate::kernel::UNROLL_FACTORS {
+                    let context = format!("{context} unroll {unroll}");
+                    assert_eq!(
+                        scalar_read(&src, stride, unroll),
+                        scalar_read(&src, stride, 1),
+                        "{context}"
+                    );
+                    assert_eq!(
+                        scalar_read_prefetch(&src, stride, unroll, 4),
+                        scalar_read(&src, stride, 1),
+                        "{context}"
+                    );
+                    let isa = crate::kernel::detect_stream_isa();
+                    assert_eq!(
+                        stream_read(&src, stride, unroll, isa),
+                        stream_read(&src, stride, 1, isa),
+                        "{context}"
+                    );
+                    assert_eq!(
+                        stream_read(&src, stride, unroll, StreamIsa::default()),
+                        scalar_read(&src, stride, 1),
+                        "{context}"
+                    );
+                    assert_eq!(write(unroll), write(1), "{context}");
+                    assert_eq!(update(unroll), update(1), "{context}");
+                    assert_eq!(copied(unroll), copied(1), "{context}");
+                    assert_eq!(both(unroll), both(1), "{context}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn two_update_passes_add_twice_the_delta() {
+        for len in LENGTHS {
+            let start: Vec<u32> = (0..len as u32).collect();
+            for stride in [1].into_iter().chain(STRIDES) {
+                let mut expected = start.clone();
+                for idx in visited(len, stride, 0) {
+                    expected[idx] += 2 * UPDATE_DELTA;
+                }
+
+                let mut scalar = start.clone();
+                scalar_update(&mut scalar, stride, 1);
+                scalar_update(&mut scalar, stride, 1);
+                assert_eq!(scalar, expected, "len {len} stride {stride}");
+
+                for offset in 0..stride {
+                    let mut expected = start.clone();
+                    for idx in visited(len, stride, offset) {
+                        expected[idx] += 2 * UPDATE_DELTA;
+                    }
+                    let mut simd = start.clone();
+                    simd_update(&mut simd, stride, offset, false);
+                    simd_update(&mut simd, stride, offset, false);
+                    assert_eq!(simd, expected, "len {len} stride {stride} offset {offset}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn simd_read_gathers_a_three_element_tail() {
+        for stride in [1].into_iter().chain(STRIDES) {
+            for offset in 0..stride {
+                for vectors in [0, 1, 5] {
+                    // Shortest and longest buffers whose strided positions from `offset` number
+                    // eight per vector plus three
+                    let positions = 8 * vectors + 3;
+                    let shortest = (positions - 1) * stride + offset + 1;
+                    for len in [shortest, shortest + stride - 1] {
+                        let slice: Vec<u32> = (0..len as u32).map(|i| (i + 1) << 8).collect();
+                        let visited: Vec<usize> = visited(len, stride, offset).collect();
+                        assert_eq!(visited.len(), positions);
+
+                        let context = format!("len {len} stride {stride} offset {offset}");
+                        let sum = simd_read(&slice, stride, offset);
+                        let expected: u64 = visited.iter().map(|&idx| slice[idx] as u64).sum();
+                        assert_eq!(sum, expected, "{context}");
+
+                        // Clearing any one tail element takes exactly its value off the sum
+                        for &idx in &visited[8 * vectors..] {
+                            let mut cleared = slice.clone();
+                            cleared[idx] = 0;
+                            assert_eq!(
+                                simd_read(&cleared, stride, offset),
+                                sum - slice[idx] as u64,
+                                "{context} tail element {idx}"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
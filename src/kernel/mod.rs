@@ -1,8 +1,12 @@
+use rand::seq::SliceRandom;
 use rand::Rng;
-use serde::Serialize;
-use std::simd::{u32x8, usizex8};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Serialize)]
+pub mod compute;
+
+pub use compute::*;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Kernel {
     // Strided access with scalar operations
     ScalarRead,
@@ -10,146 +14,442 @@ pub enum Kernel {
     // Strided access with SIMD operations
     SimdRead,
     SimdWrite,
+    // Strided access with non-temporal streaming loads
+    StreamRead,
+    // Strided access with SIMD operations on 64-bit elements
+    SimdReadU64,
+    SimdWriteU64,
+    // Strided access with SIMD operations on f32 elements
+    SimdReadF32,
+    SimdWriteF32,
+    // Uniformly random access through a precomputed index array
+    RandomRead,
+    // Strided read-modify-write (x[i] += delta)
+    ScalarUpdate,
+    SimdUpdate,
+    // Several independent dependent-load chains walked in lockstep
+    MultiChase,
+    // Tile-by-tile walk over the buffer viewed as a 2D grid, touching every element
+    Tiled2D,
+    // Contiguous blocks read at every strided position
+    BlockRead,
+    // Strided copy from one buffer into a separate destination buffer
+    Copy,
+    // Independent strided read stream from one buffer and write stream into another
+    Bidir,
+}
+
+/// Tunables shared by every pass of a kernel
+#[derive(Clone, Debug)]
+pub struct KernelParams {
+    /// Distance between accessed elements, in elements
+    pub stride: usize,
+    /// How many strided accesses ahead `scalar_read` prefetches, 0 disables prefetching
+    pub prefetch_distance: usize,
+    /// Loop unroll factor for the scalar kernels, one of `UNROLL_FACTORS`
+    pub unroll: usize,
+    /// Tile edge length in elements for `Tiled2D`
+    pub tile: usize,
+    /// Elements per grid row for `Tiled2D`
+    pub row_len: usize,
+    /// Contiguous elements read at each strided position for `BlockRead`
+    pub block_len: usize,
+    /// Use bounds-checked scatters in the SIMD write kernels
+    pub safe: bool,
+    /// Starting element within the first stride for the SIMD kernels, drawn once per run with
+    /// `random_offset` so no random number generation happens inside the timed passes. The
+    /// 64-bit kernels reduce it modulo their own element stride.
+    pub offset: usize,
+    /// Streaming loads `StreamRead` may use, from `detect_stream_isa`
+    pub stream_isa: StreamIsa,
 }
 
-pub fn scalar_read(slice: &[u32], stride: usize) -> u64 {
-    let mut sum = 0u64;
-    let len = slice.len();
+/// Parses a `--chains` value between 1 and `MAX_CHAINS`
+pub fn parse_chains(s: &str) -> Result<usize, String> {
+    let chains: usize = s.parse().map_err(|e| format!("{}", e))?;
+    if chains == 0 || chains > MAX_CHAINS {
+        return Err(format!("chain count must be between 1 and {}", MAX_CHAINS));
+    }
+    Ok(chains)
+}
 
-    // Process 4 elements per iteration
-    let unroll = 4;
-    let main_iterations = len / (stride * unroll);
-    let mut i = 0;
+/// Unroll factors accepted by the scalar kernels
+pub const UNROLL_FACTORS: [usize; 4] = [1, 2, 4, 8];
 
-    // Main loop with 4x unrolling
-    for _ in 0..main_iterations {
-        sum = sum
-            .wrapping_add(slice[i] as u64)
-            .wrapping_add(slice[i + stride] as u64)
-            .wrapping_add(slice[i + stride * 2] as u64)
-            .wrapping_add(slice[i + stride * 3] as u64);
-        i += stride * unroll;
+/// Parses an `--unroll` value, rejecting factors the scalar kernels are not built for
+pub fn parse_unroll(s: &str) -> Result<usize, String> {
+    let unroll: usize = s.parse().map_err(|e| format!("{}", e))?;
+    if !UNROLL_FACTORS.contains(&unroll) {
+        return Err(format!("unroll factor must be one of {:?}", UNROLL_FACTORS));
     }
+    Ok(unroll)
+}
 
-    // Handle remaining elements
-    while i < len {
-        sum = sum.wrapping_add(slice[i] as u64);
-        i += stride;
+/// Random starting offset within the first stride, drawn once per run so repeated runs do not
+/// always touch the same element of each line. Zero for unit (and zero) strides, which have no choice of offset.
+pub fn random_offset(stride: usize) -> usize {
+    if stride <= 1 {
+        0
+    } else {
+        rand::rng().random_range(0..stride)
     }
+}
 
-    sum
+/// Detects the streaming loads this CPU supports, once per run rather than on every pass
+pub fn detect_stream_isa() -> StreamIsa {
+    #[cfg(target_arch = "x86_64")]
+    // SAFETY: both flags come from runtime detection on this CPU
+    return unsafe {
+        StreamIsa::new_unchecked(
+            is_x86_feature_detected!("avx2"),
+            is_x86_feature_detected!("sse4.1"),
+        )
+    };
+    #[cfg(not(target_arch = "x86_64"))]
+    StreamIsa::default()
 }
 
-pub fn simd_read(slice: &[u32], stride: usize) -> u64 {
-    let mut sum: u64 = 0;
-    let offset = rand::rng().random_range(0..stride);
-    // Create indices for gather: [0*stride, 1*stride, 2*stride, ..., 15*stride]
-    let indices = usizex8::from_array(std::array::from_fn(|i| i * stride + offset));
+/// Builds `count` uniformly random element indices into a buffer of `len` elements. Computing
+/// them ahead of time keeps index generation out of the timed region.
+pub fn random_indices(len: usize, count: usize) -> Vec<u32> {
+    let mut rng = rand::rng();
+    (0..count)
+        .map(|_| rng.random_range(0..len) as u32)
+        .collect()
+}
 
-    // Process strided elements in chunks
-    let mut base = 0;
-    while base + (7 * stride) < slice.len() {
-        // Gather values from strided locations
-        sum += u32x8::gather_or_default(&slice[base..], indices).horizontal_sum() as u64;
+/// Links the elements at every `stride`-th position into `chains` disjoint random cycles of
+/// equal length, storing in each element the index of its successor. Leftover positions are
+/// left out. Returns the head of each chain, or nothing if there are fewer positions than
+/// chains.
+pub fn build_chains(slice: &mut [u32], stride: usize, chains: usize) -> Vec<u32> {
+    let mut slots: Vec<u32> = (0..slice.len()).step_by(stride).map(|i| i as u32).collect();
+    slots.shuffle(&mut rand::rng());
 
-        base += stride * 8;
+    let len = slots.len() / chains;
+    if len == 0 {
+        return Vec::new();
     }
-
-    sum
+    slots
+        .chunks_exact(len)
+        .take(chains)
+        .map(|chain| {
+            for (i, &slot) in chain.iter().enumerate() {
+                slice[slot as usize] = chain[(i + 1) % len];
+            }
+            chain[0]
+        })
+        .collect()
 }
 
-pub fn scalar_write(slice: &mut [u32], stride: usize) -> u64 {
-    let mut sum = 0u64;
-    let len = slice.len();
-
-    // Process 4 elements per iteration
-    let unroll = 4;
-    let main_iterations = len / (stride * unroll);
-    let mut i = 0;
-
-    // Main loop with 4x unrolling
-    for _ in 0..main_iterations {
-        // Write and accumulate values
-        for j in 0..unroll {
-            let idx = i + stride * j;
-            let val = (idx as u32).wrapping_mul(7); // Some deterministic value
-            slice[idx] = val;
-            sum = sum.wrapping_add(val as u64);
+impl Kernel {
+    pub fn is_simd(&self) -> bool {
+        matches!(
+            self,
+            Kernel::SimdRead
+                | Kernel::SimdWrite
+                | Kernel::StreamRead
+                | Kernel::SimdReadU64
+                | Kernel::SimdWriteU64
+                | Kernel::SimdReadF32
+                | Kernel::SimdWriteF32
+                | Kernel::SimdUpdate
+        )
+    }
+
+    /// Size in bytes of the elements the kernel loads and stores
+    pub fn element_size(&self) -> usize {
+        match self {
+            Kernel::SimdReadU64 | Kernel::SimdWriteU64 => std::mem::size_of::<u64>(),
+            Kernel::SimdReadF32 | Kernel::SimdWriteF32 => std::mem::size_of::<f32>(),
+            _ => std::mem::size_of::<u32>(),
         }
-        i += stride * unroll;
     }
 
-    // Handle remaining elements
-    while i < len {
-        let val = (i as u32).wrapping_mul(7);
-        slice[i] = val;
-        sum = sum.wrapping_add(val as u64);
-        i += stride;
+    /// Cache lines moved per access: update kernels read each line and write it back, and
+    /// copy and bidir read a source line and write a destination line
+    pub fn lines_per_access(&self) -> usize {
+        match self {
+            Kernel::ScalarUpdate | Kernel::SimdUpdate | Kernel::Copy | Kernel::Bidir => 2,
+            _ => 1,
+        }
     }
 
-    sum
-}
+    /// Arithmetic intensity for roofline plots: integer operations per byte of element data
+    /// moved, counting a read and a write of the same element separately. Reads do one add per
+    /// element, writes compute a value and add it, updates add the delta and accumulate, copy
+    /// accumulates what it moves, and bidir does a read's add plus a write's work.
+    pub fn ops_per_byte(&self) -> f64 {
+        match self {
+            Kernel::ScalarRead
+            | Kernel::SimdRead
+            | Kernel::StreamRead
+            | Kernel::SimdReadF32
+            | Kernel::RandomRead
+            | Kernel::MultiChase
+            | Kernel::Tiled2D
+            | Kernel::BlockRead => 1.0 / 4.0,
+            Kernel::SimdReadU64 => 1.0 / 8.0,
+            Kernel::ScalarWrite | Kernel::SimdWrite | Kernel::SimdWriteF32 => 2.0 / 4.0,
+            Kernel::SimdWriteU64 => 2.0 / 8.0,
+            Kernel::ScalarUpdate | Kernel::SimdUpdate => 2.0 / 8.0,
+            Kernel::Copy => 1.0 / 8.0,
+            Kernel::Bidir => 3.0 / 8.0,
+        }
+    }
 
-pub fn simd_write(slice: &mut [u32], stride: usize) -> u64 {
-    let mut sum: u64 = 0;
-    let offset = rand::rng().random_range(0..stride);
+    /// Whether the kernel's results depend on the initial contents of the buffer
+    pub fn reads_source(&self) -> bool {
+        !matches!(
+            self,
+            Kernel::ScalarWrite | Kernel::SimdWrite | Kernel::SimdWriteU64 | Kernel::SimdWriteF32
+        )
+    }
 
-    // Create indices for scatter: [0*stride, 1*stride, 2*stride, ..., 7*stride]
-    let indices = usizex8::from_array(std::array::from_fn(|i| i * stride + offset));
+    /// Whether the kernel writes into a second, separately allocated buffer
+    pub fn needs_destination(&self) -> bool {
+        matches!(self, Kernel::Copy | Kernel::Bidir)
+    }
 
-    // Create values to write: [i*7, (i+1)*7, ..., (i+7)*7]
-    let mut base = 0;
-    while base + (7 * stride) < slice.len() {
-        let values = u32x8::from_array(std::array::from_fn(|i| {
-            ((base + i * stride) as u32).wrapping_mul(7)
-        }));
+    /// Links one pass of `MultiChase` follows per chain: every chain is equally long and
+    /// together they cover every `stride`-th element
+    pub fn chase_steps(len: usize, stride: usize, chains: usize) -> usize {
+        len.div_ceil(stride) / chains.max(1)
+    }
 
-        // Scatter values to strided locations
-        unsafe {
-            values.scatter_unchecked(&mut slice[base..], indices);
+    /// Elements one pass of `BlockRead` touches: `block_len` from each block, with the last
+    /// block cut short at the end of the buffer. `block_len` must not exceed `stride`.
+    pub fn block_elements(len: usize, stride: usize, block_len: usize) -> usize {
+        let blocks = len.div_ceil(stride);
+        match blocks {
+            0 => 0,
+            _ => (blocks - 1) * block_len + block_len.min(len - (blocks - 1) * stride),
         }
-
-        sum += values.horizontal_sum() as u64;
-        base += stride * 8;
     }
 
-    sum
-}
-
-impl Kernel {
-    pub fn run(&self, slice: &mut [u32], stride: usize) -> u64 {
+    /// Runs one pass over `slice`. `dst` is only used by kernels that `needs_destination`,
+    /// and `indices` only by `RandomRead` (the elements to read) and `MultiChase` (the chain
+    /// heads). The SIMD kernels start every pass at `params.offset`. The f32 kernels
+    /// return the bits of their f64 sum.
+    pub fn run(
+        &self,
+        slice: &mut [u32],
+        dst: &mut [u32],
+        params: &KernelParams,
+        indices: &[u32],
+    ) -> u64 {
+        let stride = params.stride;
+        // Halving the element stride keeps the byte stride, and so the lines touched, the same
+        let stride_u64 = (stride / 2).max(1);
+        let offset = params.offset;
+        let offset_u64 = offset % stride_u64;
         match self {
-            Kernel::ScalarRead => scalar_read(slice, stride),
-            Kernel::ScalarWrite => scalar_write(slice, stride),
-            Kernel::SimdRead => simd_read(slice, stride),
-            Kernel::SimdWrite => simd_write(slice, stride),
+            Kernel::ScalarRead if params.prefetch_distance > 0 => {
+                scalar_read_prefetch(slice, stride, params.unroll, params.prefetch_distance)
+            }
+            Kernel::ScalarRead => scalar_read(slice, stride, params.unroll),
+            Kernel::ScalarWrite => scalar_write(slice, stride, params.unroll),
+            Kernel::SimdRead => simd_read(slice, stride, offset),
+            Kernel::StreamRead => stream_read(slice, stride, params.unroll, params.stream_isa),
+            Kernel::SimdWrite => simd_write(slice, stride, offset, params.safe),
+            Kernel::SimdReadU64 => simd_read_u64(as_u64(slice), stride_u64, offset_u64),
+            Kernel::SimdReadF32 => simd_read_f32(as_f32(slice), stride, offset).to_bits(),
+            Kernel::SimdWriteF32 => {
+                simd_write_f32(as_f32(slice), stride, offset, params.safe).to_bits()
+            }
+            Kernel::SimdWriteU64 => {
+                simd_write_u64(as_u64(slice), stride_u64, offset_u64, params.safe)
+            }
+            Kernel::RandomRead => random_read(slice, indices),
+            Kernel::MultiChase => multi_chase(
+                slice,
+                indices,
+                Kernel::chase_steps(slice.len(), stride, indices.len()),
+            ),
+            Kernel::ScalarUpdate => scalar_update(slice, stride, params.unroll),
+            Kernel::SimdUpdate => simd_update(slice, stride, offset, params.safe),
+            Kernel::Tiled2D => tiled_2d(slice, params.row_len, params.tile),
+            Kernel::BlockRead => block_read(slice, stride, params.block_len),
+            Kernel::Copy => copy(slice, dst, stride, params.unroll),
+            Kernel::Bidir => bidir(slice, dst, stride, params.unroll),
         }
     }
 }
 
-// Add horizontal_sum for u32x16
-trait SimdExt {
-    fn horizontal_sum(self) -> u32;
+/// Views a u32 buffer as u64 elements. Only the 8-byte aligned middle is returned, which is the
+/// whole buffer for any `--align` of 8 or more.
+fn as_u64(slice: &mut [u32]) -> &mut [u64] {
+    // SAFETY: every bit pattern is a valid u64, and align_to_mut only yields aligned elements
+    unsafe { slice.align_to_mut::<u64>().1 }
 }
 
-impl SimdExt for u32x8 {
-    fn horizontal_sum(self) -> u32 {
-        let arr = self.to_array();
-        arr.iter().sum()
-    }
+/// Views a u32 buffer as f32 elements, which have the same size and alignment
+fn as_f32(slice: &mut [u32]) -> &mut [f32] {
+    // SAFETY: every bit pattern is a valid f32 and the layouts match, so nothing is left over
+    unsafe { slice.align_to_mut::<f32>().1 }
 }
 
-// Add scatter support for u32x8
-trait SimdScatter {
-    unsafe fn scatter_unchecked(self, slice: &mut [u32], indices: usizex8);
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEN: usize = 1000;
+    const STRIDE: usize = 16;
+    const OFFSET: usize = 5;
+
+    fn params(stride: usize, offset: usize) -> KernelParams {
+        KernelParams {
+            stride,
+            prefetch_distance: 0,
+            unroll: 1,
+            tile: 1,
+            row_len: 1,
+            block_len: 1,
+            safe: false,
+            offset,
+            stream_isa: detect_stream_isa(),
+        }
+    }
+
+    /// Runs `kernel` once over `0..LEN` at the fixed offset, returning its checksum and buffer
+    fn run_at_offset(kernel: Kernel) -> (u64, Vec<u32>) {
+        let mut slice: Vec<u32> = (0..LEN as u32).collect();
+        let sum = kernel.run(&mut slice, &mut [], &params(STRIDE, OFFSET), &[]);
+        (sum, slice)
+    }
+
+    fn visited(len: usize, stride: usize, offset: usize) -> impl Iterator<Item = usize> {
+        (offset..len).step_by(stride)
+    }
+
+    #[test]
+    fn simd_read_starts_at_params_offset() {
+        let expected: u64 = visited(LEN, STRIDE, OFFSET).map(|idx| idx as u64).sum();
+        assert_eq!(run_at_offset(Kernel::SimdRead).0, expected);
+    }
+
+    #[test]
+    fn simd_write_starts_at_params_offset() {
+        let (sum, slice) = run_at_offset(Kernel::SimdWrite);
+
+        let mut expected: Vec<u32> = (0..LEN as u32).collect();
+        let mut expected_sum = 0u64;
+        for idx in visited(LEN, STRIDE, OFFSET) {
+            expected[idx] = ((idx - OFFSET) as u32).wrapping_mul(7);
+            expected_sum += expected[idx] as u64;
+        }
+        assert_eq!(slice, expected);
+        assert_eq!(sum, expected_sum);
+    }
+
+    #[test]
+    fn simd_update_starts_at_params_offset() {
+        let (sum, slice) = run_at_offset(Kernel::SimdUpdate);
+
+        let mut expected: Vec<u32> = (0..LEN as u32).collect();
+        let mut expected_sum = 0u64;
+        for idx in visited(LEN, STRIDE, OFFSET) {
+            expected[idx] += UPDATE_DELTA;
+            expected_sum += expected[idx] as u64;
+        }
+        assert_eq!(slice, expected);
+        assert_eq!(sum, expected_sum);
+    }
+
+    #[test]
+    fn simd_read_f32_starts_at_params_offset() {
+        let mut slice: Vec<u32> = (0..LEN).map(|i| (i as f32).to_bits()).collect();
+        let sum = Kernel::SimdReadF32.run(&mut slice, &mut [], &params(STRIDE, OFFSET), &[]);
+
+        // Small integers are exact in f32, so the sum is exact too
+        let expected: f64 = visited(LEN, STRIDE, OFFSET).map(|idx| idx as f64).sum();
+        assert_eq!(f64::from_bits(sum), expected);
+    }
+
+    #[test]
+    fn simd_write_f32_starts_at_params_offset() {
+        let (sum, slice) = run_at_offset(Kernel::SimdWriteF32);
+
+        let mut expected: Vec<u32> = (0..LEN as u32).collect();
+        let mut expected_sum = 0.0f64;
+        for idx in visited(LEN, STRIDE, OFFSET) {
+            let value = (idx - OFFSET) as f32 * 0.5;
+            expected[idx] = value.to_bits();
+            expected_sum += value as f64;
+        }
+        assert_eq!(slice, expected);
+        assert_eq!(f64::from_bits(sum), expected_sum);
+    }
+
+    #[test]
+    fn u64_kernels_reduce_the_offset_to_their_own_stride() {
+        // 16 u32 elements are 8 u64 elements, so offset 13 starts at u64 element 5
+        let stride_u64 = STRIDE / 2;
+        let offset = 13;
+
+        let mut slice: Vec<u32> = (0..LEN as u32).collect();
+        let sum = Kernel::SimdReadU64.run(&mut slice, &mut [], &params(STRIDE, offset), &[]);
+        let wide = as_u64(&mut slice);
+        let expected = visited(wide.len(), stride_u64, offset % stride_u64)
+            .fold(0u64, |acc, idx| acc.wrapping_add(wide[idx]));
+        assert_eq!(sum, expected);
+
+        let sum = Kernel::SimdWriteU64.run(&mut slice, &mut [], &params(STRIDE, offset), &[]);
+        let wide = as_u64(&mut slice);
+        let mut expected_sum = 0u64;
+        for idx in visited(wide.len(), stride_u64, offset % stride_u64) {
+            let value = ((idx - offset % stride_u64) as u64).wrapping_mul(7);
+            assert_eq!(wide[idx], value, "u64 element {idx}");
+            expected_sum = expected_sum.wrapping_add(value);
+        }
+        assert_eq!(sum, expected_sum);
+    }
+
+    #[test]
+    fn repeated_passes_at_a_fixed_offset_match() {
+        for kernel in [Kernel::SimdRead, Kernel::SimdReadU64, Kernel::SimdReadF32] {
+            let mut slice: Vec<u32> = (0..LEN as u32).collect();
+            let first = kernel.run(&mut slice, &mut [], &params(STRIDE, OFFSET), &[]);
+            let second = kernel.run(&mut slice, &mut [], &params(STRIDE, OFFSET), &[]);
+            assert_eq!(first, second, "{:?}", kernel);
+        }
+    }
+
+    #[test]
+    fn block_read_touches_block_elements() {
+        // Every element is one, so the checksum counts the elements read
+        let slice = vec![1u32; LEN];
+        for stride in [1, 7, 16, 100, 999, 1000, 1500] {
+            // --block-len may not exceed the stride, so blocks never overlap
+            for block_len in [1, 3, 7, 16, 64].into_iter().filter(|&b| b <= stride) {
+                let params = KernelParams {
+                    block_len,
+                    ..params(stride, 0)
+                };
+                let touched = Kernel::BlockRead.run(&mut slice.clone(), &mut [], &params, &[]);
+                let blocks = LEN.div_ceil(stride);
+                let expected = Kernel::block_elements(LEN, stride, block_len);
+                assert_eq!(
+                    touched as usize, expected,
+                    "stride {stride} block {block_len}"
+                );
 
-impl SimdScatter for u32x8 {
-    unsafe fn scatter_unchecked(self, slice: &mut [u32], indices: usizex8) {
-        let values = self.to_array();
-        let idx = indices.to_array();
-        for i in 0..8 {
-            *slice.get_unchecked_mut(idx[i]) = values[i];
+                // Only a block starting within block_len of the end is cut short
+                let last_start = (blocks - 1) * stride;
+                if last_start + block_len <= LEN {
+                    assert_eq!(
+                        expected,
+                        blocks * block_len,
+                        "stride {stride} block {block_len}"
+                    );
+                } else {
+                    assert_eq!(
+                        expected,
+                        (blocks - 1) * block_len + LEN - last_start,
+                        "stride {stride} block {block_len}"
+                    );
+                }
+            }
         }
     }
 }
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+mod progress;
+
+pub use progress::{progress_enabled, ProgressLine};
+
+/// How much diagnostic output goes to stderr. Formatted results are always written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only the formatted results
+    Quiet,
+    /// Results plus a one-line summary per run and warnings
+    Normal,
+    /// Everything, including cache information and bandwidth calculation details
+    Verbose,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Verbosity) -> bool {
+    VERBOSITY.load(Ordering::Relaxed) >= level as u8
+}
+
+/// Prints to stderr unless `--quiet` is set
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Verbosity::Normal) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Prints a `Warning:`-prefixed line to stderr unless `--quiet` is set
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Verbosity::Normal) {
+            eprintln!("Warning: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+/// Prints to stderr only when `--verbose` is set
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Verbosity::Verbose) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use {info, verbose, warning};
@@ -0,0 +1,34 @@
+use std::io::{self, IsTerminal, Write};
+
+use super::{enabled, Verbosity};
+
+/// Whether to draw progress: not under `--quiet`, and only when stderr is a terminal so
+/// redirected logs are not filled with carriage returns
+pub fn progress_enabled() -> bool {
+    enabled(Verbosity::Normal) && io::stderr().is_terminal()
+}
+
+/// A single status line on stderr, redrawn in place with a carriage return
+#[derive(Default)]
+pub struct ProgressLine {
+    width: usize,
+}
+
+impl ProgressLine {
+    /// Replaces the line with `text`, padding over whatever longer text was there before
+    pub fn update(&mut self, text: &str) {
+        let pad = self.width.saturating_sub(text.len());
+        eprint!("\r{}{}", text, " ".repeat(pad));
+        let _ = io::stderr().flush();
+        self.width = text.len();
+    }
+
+    /// Blanks the line so the next output starts on a clean one
+    pub fn clear(&mut self) {
+        if self.width > 0 {
+            eprint!("\r{}\r", " ".repeat(self.width));
+            let _ = io::stderr().flush();
+            self.width = 0;
+        }
+    }
+}
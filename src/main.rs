@@ -1,19 +1,50 @@
 #![feature(portable_simd)]
-use clap::Parser;
-use core_affinity::{get_core_ids, set_for_current};
-use rand::Rng;
+use clap::builder::RangedU64ValueParser;
+use clap::{CommandFactory, Parser, ValueEnum};
+use core_affinity::{get_core_ids, set_for_current, CoreId};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::Barrier;
 use std::thread;
 use std::time::{Duration, Instant};
 
+mod buffer;
+mod config;
 mod kernel;
+mod log;
 mod report;
 mod system;
 
-use kernel::Kernel;
-use report::{print_results, BenchmarkConfig, BenchmarkResult, BenchmarkResults};
-use system::cpu_info::get_cpu_info;
+use buffer::{parse_alignment, parse_fill, prefault, Buffer, Fill, MemType};
+use config::ConfigFile;
+use kernel::{
+    build_chains, detect_stream_isa, parse_chains, parse_unroll, random_indices, random_offset,
+    Kernel, KernelParams,
+};
+use log::{info, progress_enabled, set_verbosity, verbose, warning, ProgressLine, Verbosity};
+use report::compare::{compare, load_baseline, print_comparison};
+use report::stats::{
+    relative_standard_error, steady_state, AutoDuration, PassTimings, SteadyCriterion,
+    TimingHistogram,
+};
+use report::{
+    append_csv, format_size, group_results, ndjson_line, print_plan, print_results, Accounting,
+    BenchmarkConfig, BenchmarkResult, BenchmarkResults, CcdPlacement, Summary, ThreadPlacement,
+    Units, FORMATS, SCHEMA_VERSION,
+};
+use system::clock::timestamp_rfc3339;
+use system::cpu_info::{get_cpu_info, CacheInfo};
+use system::host::get_machine_info;
+use system::memory::detect_peak_gbps;
+use system::perf::{PerfCounters, PerfCounts};
+use system::signal::{install_interrupt_handler, interrupted};
+use system::topology::{current_cpu, detect_topology, l3_domains, Topology};
+use system::{numa, sched};
 
 #[derive(Clone, Debug, clap::ValueEnum)]
 pub enum Operation {
@@ -23,24 +54,101 @@ pub enum Operation {
     Write,
     /// Read and write benchmark
     ReadWrite,
+    /// Read-modify-write benchmark (x[i] += delta)
+    Update,
+    /// Copy from a source buffer into a separate destination buffer
+    Copy,
+    /// Read one buffer while writing another, as independent concurrent streams
+    Bidir,
+}
+
+/// Element type the SIMD kernels operate on
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Dtype {
+    U32,
+    U64,
+    F32,
+}
+
+/// Geometric range of byte strides for `--stride-sweep`
+#[derive(Clone, Debug)]
+struct StrideSweep {
+    start: usize,
+    end: usize,
+    mult: usize,
+}
+
+impl StrideSweep {
+    /// Strides from `start` up to and including `end`, multiplying by `mult` each step
+    fn strides(&self) -> Vec<usize> {
+        std::iter::successors(Some(self.start), |&stride| stride.checked_mul(self.mult))
+            .take_while(|&stride| stride <= self.end)
+            .collect()
+    }
+}
+
+/// Parses a `--stride-sweep` value of the form `start:end:mult`
+fn parse_stride_sweep(s: &str) -> Result<StrideSweep, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let [start, end, mult] = parts.as_slice() else {
+        return Err("expected start:end:mult".to_string());
+    };
+    let parse = |v: &str| v.parse::<usize>().map_err(|e| format!("{}: {}", v, e));
+    let sweep = StrideSweep {
+        start: parse(start)?,
+        end: parse(end)?,
+        mult: parse(mult)?,
+    };
+    if sweep.start == 0 || sweep.end < sweep.start || sweep.mult < 2 {
+        return Err("start must be at least 1, end at least start and mult at least 2".to_string());
+    }
+    Ok(sweep)
 }
 
 #[derive(Parser)]
-#[command(author, version, about = "Memory bandwidth benchmark tool")]
+#[command(
+    author,
+    version,
+    about = "Memory bandwidth benchmark tool",
+    args_override_self = true
+)]
 struct Args {
+    /// Read default arguments from a TOML file of long option names and values, e.g.
+    /// `stride = 128`, `operation = "write"`, `simd = true` (a JSON object if the name ends in
+    /// .json). Command-line flags take precedence, including over options they conflict with.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
     /// Buffer size in MiB (fixed at 128 MiB)
     #[arg(short, long, default_value_t = 128, hide = true)]
     size: usize,
 
     /// Stride size in bytes (defaults to CPU's cache line size, specify explicitly to override)
-    #[arg(long)]
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
     stride: Option<usize>,
 
+    /// Run once per byte stride in a geometric range, given as start:end:mult (e.g. 64:4096:2)
+    #[arg(long, value_parser = parse_stride_sweep, conflicts_with = "stride")]
+    stride_sweep: Option<StrideSweep>,
+
     /// Duration of measurement in seconds
     #[arg(short, long, default_value_t = 10.0)]
     duration: f64,
 
-    /// Operation type (read, write, or readwrite)
+    /// Run exactly this many timed kernel passes per thread instead of for --duration
+    #[arg(long, conflicts_with = "duration", value_parser = clap::value_parser!(u64).range(1..))]
+    iterations: Option<u64>,
+
+    /// Keep a run going past --duration until at least this many timed passes, summed over
+    /// threads, have completed
+    #[arg(
+        long,
+        conflicts_with_all = ["iterations", "auto_duration"],
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    min_iterations: Option<u64>,
+
+    /// Operation type (read, write, read-write, update, copy or bidir)
     #[arg(short, long, value_enum, default_value_t = Operation::Read)]
     operation: Operation,
 
@@ -48,10 +156,55 @@ struct Args {
     #[arg(short, long, default_value_t = 5)]
     warmup: usize,
 
+    /// Warm up for this many seconds instead of a fixed number of iterations
+    #[arg(long, conflicts_with = "warmup")]
+    warmup_secs: Option<f64>,
+
+    /// Warm up with this operation (read, write or update) instead of the measured one, e.g. a
+    /// write pass before timing reads to measure reading back dirty lines. Follows --simd.
+    #[arg(long, value_enum)]
+    warmup_kernel: Option<Operation>,
+
     /// Enable SIMD reads
     #[arg(long)]
     simd: bool,
 
+    /// Read at uniformly random indices instead of strided (read operation only)
+    #[arg(long)]
+    random: bool,
+
+    /// Software prefetch distance for the scalar read kernel, in strided accesses (0 disables)
+    #[arg(long, default_value_t = 0)]
+    prefetch: usize,
+
+    /// Loop unroll factor for the scalar kernels (1, 2, 4 or 8)
+    #[arg(long, default_value_t = 4, value_parser = parse_unroll)]
+    unroll: usize,
+
+    /// Theoretical peak DRAM bandwidth in GB/s (channels x MT/s x 8 bytes / 1000), used to
+    /// report efficiency. Detected from SMBIOS DIMM info on Linux when omitted and readable.
+    #[arg(long)]
+    peak_gbps: Option<f64>,
+
+    /// Number of populated memory channels; also reports bandwidth (and efficiency against
+    /// --peak-gbps) per channel
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    channels: Option<u64>,
+
+    /// Flag results as unreliable when the measured time strays from --duration by more than
+    /// this many percent
+    #[arg(long, default_value_t = 20.0)]
+    tolerance: f64,
+
+    /// How bytes moved are counted: lines (a whole cache line per access) or bytes (only the
+    /// element bytes the kernel references, as STREAM does)
+    #[arg(long, value_enum, default_value_t = Accounting::Lines)]
+    accounting: Accounting,
+
+    /// Units for reported bandwidth: gib (2^30 bytes/s) or gb (10^9 bytes/s)
+    #[arg(long, value_enum, default_value_t = Units::Gib)]
+    units: Units,
+
     /// Enable parallel processing
     #[arg(short, long)]
     parallel: bool,
@@ -60,196 +213,1557 @@ struct Args {
     #[arg(short, long)]
     threads: Option<usize>,
 
-    /// Output format (text, csv, json)
+    /// Output format (text, csv, json, ndjson, yaml, prometheus, markdown, plot). `ndjson` writes
+    /// one line per result as soon as each run finishes. `plot` emits two columns for gnuplot:
+    /// thread count, stride or size in bytes vs bandwidth, depending on what varies
     #[arg(short, long, default_value = "text")]
     format: String,
 
+    /// Memory type of the buffers. `wc` runs the write kernels against write-combining memory
+    /// mapped from --wc-path, which is Linux only and usually needs root
+    #[arg(long, value_enum, default_value_t = MemType::Normal, requires_if("wc", "wc_path"))]
+    memtype: MemType,
+
+    /// Device file mapped as write-combining memory for --memtype wc, e.g. a PCI BAR's
+    /// /sys/bus/pci/devices/<device>/resource<N>_wc. All threads share the mapped region.
+    #[arg(long, conflicts_with = "hugepages")]
+    wc_path: Option<PathBuf>,
+
+    /// Map the buffers from this file instead of anonymous memory, e.g. a file on tmpfs or a
+    /// DAX filesystem, or a device DAX node. A regular file is created if absent and grown to
+    /// one page-aligned region per thread. Write kernels overwrite its contents; the copy
+    /// destination stays in anonymous memory.
+    #[arg(long, conflicts_with_all = ["hugepages", "wc_path"])]
+    mmap_file: Option<PathBuf>,
+
+    /// Print the detected caches, cores and NUMA nodes and exit without measuring
+    #[arg(long)]
+    list_topology: bool,
+
+    /// Print what this build and CPU support (ISA features, platform features, formats,
+    /// operations and element types) as JSON and exit without measuring
+    #[arg(long)]
+    capabilities: bool,
+
+    /// Print every run that would be executed, with the resolved configuration, and exit
+    /// without measuring
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Omit the header line from CSV output and from new --append files, for scripts that
+    /// collect rows across runs
+    #[arg(long)]
+    csv_no_header: bool,
+
+    /// Add a `groups` field to JSON and YAML output that nests the results by each dimension
+    /// the run swept (size, stride, NUMA pair, CCD placement, kernel), keeping the flat
+    /// `results` array alongside
+    #[arg(long)]
+    group_results: bool,
+
+    /// Write formatted results to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Append one CSV row per result (with timestamp and hostname) to this file
+    #[arg(long)]
+    append: Option<PathBuf>,
+
+    /// Compare bandwidth against results previously saved with --format json
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Exit with an error if any result is more than this percent slower than the baseline, or
+    /// if no result matches the baseline at all
+    #[arg(long, requires = "baseline")]
+    fail_under: Option<f64>,
+
+    /// Exit with code 2 if any result's bandwidth is below this value (in --units)
+    #[arg(long)]
+    min_bandwidth: Option<f64>,
+
+    /// Only print the formatted results
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print cache information and detailed bandwidth calculations
+    #[arg(short, long)]
+    verbose: bool,
+
     /// Enable core affinity
     #[arg(long)]
     affinity: bool,
+
+    /// Pin to one logical CPU per physical core, skipping SMT siblings
+    #[arg(long, requires = "affinity")]
+    physical_cores_only: bool,
+
+    /// Measure every pair of NUMA nodes, running the threads on the CPUs of one node with their
+    /// buffers bound to the memory of the other, and report the bandwidth matrix
+    #[arg(long, conflicts_with_all = ["affinity", "levels", "stride_sweep"])]
+    numa_matrix: bool,
+
+    /// Run the threads twice, packed onto the cores of one L3 domain (CCX/CCD) and spread
+    /// across all of them, and report the two bandwidths side by side
+    #[arg(long, requires = "parallel", conflicts_with_all = ["affinity", "numa_matrix"])]
+    ccd_placement: bool,
+
+    /// Run the scalar and SIMD read and write kernels back to back with the same configuration
+    /// and report the SIMD speedup over scalar for each operation
+    #[arg(
+        long = "compare",
+        conflicts_with_all = [
+            "operation",
+            "simd",
+            "random",
+            "streaming",
+            "chains",
+            "tile",
+            "block_len"
+        ]
+    )]
+    compare_kernels: bool,
+
+    /// Measure L1, L2, L3 and DRAM bandwidth using buffer sizes derived from the detected caches
+    #[arg(long)]
+    levels: bool,
+
+    /// Size the buffer to this many KiB so it stays resident in a cache level, and report which
+    /// level it fits in
+    #[arg(
+        long,
+        conflicts_with = "levels",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    resident: Option<usize>,
+
+    /// Buffer alignment in bytes, a power of two of at least 4 (defaults to the cache line size)
+    #[arg(long, value_parser = parse_alignment)]
+    align: Option<usize>,
+
+    /// Back buffers with 2 MiB huge pages (Linux only, requires reserved huge pages)
+    #[arg(long)]
+    hugepages: bool,
+
+    /// Skip pre-faulting buffers so the measurement includes cold page faults. Kernels that
+    /// read the buffer still get their --fill, which touches every page unless it is zero.
+    #[arg(long)]
+    no_prefault: bool,
+
+    /// Initial buffer contents for kernels that read it: random, zero, sequential or a hex
+    /// constant such as 0xdeadbeef. Write kernels skip the fill; --no-prefault does not.
+    #[arg(long, default_value = "random", value_parser = parse_fill)]
+    fill: Fill,
+
+    /// Count LLC misses, instructions and cycles during the timed region and report misses per
+    /// iteration and IPC (Linux only, subject to perf_event_paranoid). Counters the PMU does not
+    /// provide are left out.
+    #[arg(long)]
+    perf: bool,
+
+    /// Run the worker threads under SCHED_FIFO at this real-time priority (1-99, Linux only),
+    /// for lower-noise measurements alongside --affinity. Needs CAP_SYS_NICE; without it the
+    /// run continues at normal priority. Spinning real-time threads can starve the rest of the
+    /// system, so keep runs short (a plain --duration rather than open-ended --auto-duration or
+    /// --min-iterations) and leave a core free.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=99))]
+    rt_priority: Option<u8>,
+
+    /// Element type for the SIMD kernels (u32, u64 or f32). u64 and f32 require --simd with read
+    /// or write.
+    #[arg(long, value_enum, default_value_t = Dtype::U32)]
+    dtype: Dtype,
+
+    /// Walk the buffer as a 2D grid in tiles of this many elements per side instead of striding
+    #[arg(long)]
+    tile: Option<NonZeroUsize>,
+
+    /// Elements per grid row for --tile
+    #[arg(long, default_value = "1024", requires = "tile")]
+    row_len: NonZeroUsize,
+
+    /// Read this many contiguous elements at every --stride position instead of one, modelling
+    /// blocked-sparse access. Must not exceed the stride.
+    #[arg(long)]
+    block_len: Option<NonZeroUsize>,
+
+    /// Use bounds-checked scatters in the SIMD write and update kernels, trading speed for a
+    /// guaranteed panic instead of memory corruption if an index is ever miscomputed
+    #[arg(long)]
+    safe: bool,
+
+    /// Read with non-temporal streaming loads (x86 SSE4.1/AVX2 MOVNTDQA), falling back to
+    /// scalar reads elsewhere
+    #[arg(long)]
+    streaming: bool,
+
+    /// Chase this many independent random pointer chains in lockstep (1 to 64), measuring how
+    /// much memory-level parallelism the machine sustains
+    #[arg(long, value_parser = parse_chains)]
+    chains: Option<usize>,
+
+    /// Sample aggregate bandwidth every this many milliseconds during the timed region, to
+    /// detect thermal throttling on long runs
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    sample_interval: Option<u64>,
+
+    /// Report the mean bandwidth of the sampling windows once they reach steady state, with a
+    /// 95% confidence interval, instead of the average over the whole timed region. Samples
+    /// every 100 ms unless --sample-interval is set.
+    #[arg(long, conflicts_with = "iterations")]
+    steady_state: bool,
+
+    /// Keep measuring past --duration, in sampling windows, until the relative standard error
+    /// of the window bandwidths drops below --target-rse or --max-duration is reached. Samples
+    /// every 100 ms unless --sample-interval is set.
+    #[arg(long, conflicts_with = "iterations")]
+    auto_duration: bool,
+
+    /// Relative standard error, in percent, that ends an --auto-duration run
+    #[arg(long, default_value_t = 1.0, requires = "auto_duration")]
+    target_rse: f64,
+
+    /// Longest an --auto-duration run may extend to, in seconds
+    #[arg(long, default_value_t = 60.0, requires = "auto_duration")]
+    max_duration: f64,
+
+    /// Record a histogram of individual pass durations (log-spaced buckets, merged across
+    /// threads) in the JSON results. Costs two clock reads per pass, tens of nanoseconds, which
+    /// only matters for buffers small enough that a pass takes a few microseconds.
+    #[arg(long)]
+    record_timings: bool,
+
+    /// Consecutive windows that must agree for steady state
+    #[arg(long, default_value_t = 5, requires = "steady_state",
+          value_parser = clap::value_parser!(u64).range(2..))]
+    steady_windows: u64,
+
+    /// Coefficient of variation, in percent, below which consecutive windows count as steady
+    #[arg(long, default_value_t = 2.0, requires = "steady_state")]
+    steady_cv: f64,
+
+    /// Flag a run as throttled when the last sampling window is this many percent slower than
+    /// the first
+    #[arg(long, default_value_t = 10.0, requires = "sample_interval")]
+    throttle_threshold: f64,
+}
+
+/// Sampling window length for --steady-state and --auto-duration when --sample-interval is not
+/// given
+const DEFAULT_SAMPLE_INTERVAL_MS: u64 = 100;
+
+/// Fewest timed passes, summed over threads, for a result to count as reliable
+const MIN_RELIABLE_ITERATIONS: usize = 10;
+
+/// One measurement of the invocation: a buffer size (labelled with its cache level under
+/// --levels), a stride, under --numa-matrix the CPU node and memory node, under
+/// --ccd-placement the thread layout, and the kernel (which varies under --compare)
+struct Run {
+    level: Option<String>,
+    size: usize,
+    stride_bytes: usize,
+    numa: Option<(usize, usize)>,
+    ccd: Option<CcdPlacement>,
+    kernel: Kernel,
+}
+
+/// Kernels --compare runs, each SIMD kernel following its scalar counterpart
+const COMPARE_KERNELS: [Kernel; 4] = [
+    Kernel::ScalarRead,
+    Kernel::SimdRead,
+    Kernel::ScalarWrite,
+    Kernel::SimdWrite,
+];
+
+/// Working-set sizes used by `--levels` when cache sizes cannot be detected
+const FALLBACK_LEVEL_SIZES: [(&str, usize); 4] = [
+    ("L1", 16 * 1024),
+    ("L2", 128 * 1024),
+    ("L3", 4 * 1024 * 1024),
+    ("DRAM", 256 * 1024 * 1024),
+];
+
+/// What each worker thread reports back after the timed region
+struct ThreadResult {
+    checksum: u64,
+    iterations: usize,
+    /// Time from this thread's start to the end of its last iteration
+    elapsed: Duration,
+    /// Elapsed time shared by all threads, only reported by the first thread
+    shared_elapsed: Option<Duration>,
+    /// Whether the run went past its duration to reach --min-iterations, only reported by the
+    /// first thread
+    extended: bool,
+    huge_pages: bool,
+    /// Time spent allocating, filling and pre-faulting this thread's buffers
+    setup: Duration,
+    /// Hardware counter totals for the timed region, when --perf was requested
+    perf: Option<io::Result<PerfCounts>>,
+    /// Outcome of binding the thread's memory to `config.mem_node`, `Ok` when unset
+    mem_bind: io::Result<()>,
+    /// Outcome of switching to SCHED_FIFO, when --rt-priority was requested
+    realtime: Option<io::Result<()>>,
+    /// Durations of this thread's timed passes, under --record-timings
+    timings: Option<PassTimings>,
+    placement: ThreadPlacement,
+}
+
+/// Aggregate outcome of one `measure_memory_bandwidth` run
+struct Measurement {
+    /// Aggregate bandwidth in the configured units
+    bandwidth: f64,
+    elapsed_secs: f64,
+    interrupted: bool,
+    /// Whether the run was extended past its duration to reach --min-iterations
+    extended: bool,
+    /// Whether every worker ran under SCHED_FIFO
+    realtime: bool,
+    /// When the measurement started, RFC3339
+    timestamp: String,
+    iterations: usize,
+    huge_pages: bool,
+    per_thread: Vec<f64>,
+    perf: Option<PerfCounts>,
+    /// Aggregate bandwidth of each sampling window, empty unless --sample-interval is set
+    windows: Vec<f64>,
+    /// Pass durations merged across threads, under --record-timings
+    timings: Option<TimingHistogram>,
+    placement: Vec<ThreadPlacement>,
+}
+
+/// Bytes one kernel pass moves under the configured `Accounting`, returned with the elements
+/// accessed per pass and the cache line size the line count is based on
+fn bytes_per_iteration(config: &BenchmarkConfig) -> (usize, usize, usize) {
+    let (elements, cache_line_size, line_bytes) = line_bytes_per_iteration(config);
+    let bytes = match config.accounting {
+        Accounting::Lines => line_bytes,
+        Accounting::Bytes => referenced_bytes_per_iteration(config),
+    };
+    (elements, cache_line_size, bytes)
+}
+
+/// Bytes of the elements one kernel pass references, counting every element once per access
+/// (twice for kernels that read and write it) however many share a cache line
+fn referenced_bytes_per_iteration(config: &BenchmarkConfig) -> usize {
+    let element_size = config.kernel.element_size();
+    let num_elements = config.size / std::mem::size_of::<u32>();
+    match config.kernel {
+        Kernel::Tiled2D => num_elements * element_size,
+        Kernel::BlockRead => {
+            let block_len = config.block_len.unwrap_or(1);
+            Kernel::block_elements(num_elements, config.stride, block_len) * element_size
+        }
+        Kernel::MultiChase => {
+            let chains = config.chains.unwrap_or(1);
+            Kernel::chase_steps(num_elements, config.stride, chains) * chains * element_size
+        }
+        _ => {
+            // Wider elements keep the byte stride, as the u64 kernels do
+            let stride = (config.stride * std::mem::size_of::<u32>() / element_size).max(1);
+            let accessed = (config.size / element_size).div_ceil(stride);
+            accessed * element_size * config.kernel.lines_per_access()
+        }
+    }
+}
+
+/// Bytes one `Bidir` pass reads from its source and writes to its destination under the
+/// configured `Accounting`. Each stream makes one access per strided position of its own
+/// buffer, so together they make up `bytes_per_iteration`.
+fn bidir_stream_bytes(config: &BenchmarkConfig) -> (usize, usize) {
+    let (accesses, cache_line_size, _) = line_bytes_per_iteration(config);
+    let element_size = config.kernel.element_size();
+    let read = match config.accounting {
+        Accounting::Lines => {
+            lines_spanned(accesses, config.stride, element_size, cache_line_size) * cache_line_size
+        }
+        Accounting::Bytes => accesses * element_size,
+    };
+    // The destination is as long as the source and written at the same positions
+    let write = read;
+    (read, write)
+}
+
+/// Bytes one kernel pass moves counting a whole cache line per access, with the elements
+/// accessed per pass and the line size
+fn line_bytes_per_iteration(config: &BenchmarkConfig) -> (usize, usize, usize) {
+    let cache_line_size = config.cpu_cache_info.l1d_line_size.unwrap_or(64);
+    let element_size = config.kernel.element_size();
+    let num_elements = config.size / std::mem::size_of::<u32>();
+
+    // Elements accessed with stride, in the kernel's own element size: wider elements keep the
+    // byte stride, as the u64 kernels do
+    let stride = (config.stride * std::mem::size_of::<u32>() / element_size).max(1);
+    let elements_per_iteration = (config.size / element_size).div_ceil(stride);
+
+    // Each access fetches exactly one cache line, regardless of stride, and update kernels
+    // also write that line back
+    // The tiled kernel reads every element once per pass rather than one element per stride,
+    // and the chase kernel skips the elements left over after splitting them into chains
+    let bytes = match config.kernel {
+        Kernel::Tiled2D => {
+            lines_spanned(num_elements, 1, std::mem::size_of::<u32>(), cache_line_size)
+                * cache_line_size
+        }
+        // Every line a block spans is fetched once, however many blocks share it
+        Kernel::BlockRead => {
+            let block_len = config.block_len.unwrap_or(1);
+            let mut lines = 0;
+            let mut next_line = 0;
+            for start in (0..num_elements).step_by(config.stride) {
+                let end = (start + block_len).min(num_elements);
+                let first = (start * std::mem::size_of::<u32>() / cache_line_size).max(next_line);
+                let last = (end * std::mem::size_of::<u32>() - 1) / cache_line_size;
+                if last >= first {
+                    lines += last - first + 1;
+                    next_line = last + 1;
+                }
+            }
+            lines * cache_line_size
+        }
+        Kernel::MultiChase => {
+            let chains = config.chains.unwrap_or(1);
+            Kernel::chase_steps(num_elements, config.stride, chains) * chains * cache_line_size
+        }
+        _ => {
+            lines_spanned(
+                elements_per_iteration,
+                stride,
+                element_size,
+                cache_line_size,
+            ) * cache_line_size
+                * config.kernel.lines_per_access()
+        }
+    };
+    (elements_per_iteration, cache_line_size, bytes)
+}
+
+/// Distinct cache lines touched by `accesses` elements `stride` elements apart, starting at a
+/// line boundary. Strides of a line or more touch a line per access; shorter ones touch every
+/// line up to the last access.
+fn lines_spanned(accesses: usize, stride: usize, element_size: usize, line: usize) -> usize {
+    let stride_bytes = stride * element_size;
+    match accesses {
+        0 => 0,
+        _ if stride_bytes >= line => accesses,
+        _ => (accesses - 1) * stride_bytes / line + 1,
+    }
+}
+
+/// Whether bandwidth dropped by more than `threshold_percent` from the first sampling window
+/// to the last. Needs at least two windows.
+fn throttled(windows: &[f64], threshold_percent: f64) -> bool {
+    match (windows.first(), windows.last()) {
+        (Some(&first), Some(&last)) if windows.len() >= 2 && first > 0.0 => {
+            (first - last) / first * 100.0 > threshold_percent
+        }
+        _ => false,
+    }
+}
+
+/// Iteration count of one worker thread, padded to its own cache line so the monitors' reads
+/// do not false-share with the other workers
+#[repr(align(128))]
+#[derive(Default)]
+struct IterationCounter(AtomicUsize);
+
+/// Samples aggregate bandwidth in windows of `interval` for the length of the timed region,
+/// from the iteration counts the workers publish. Only complete windows are returned. Under
+/// --auto-duration this also ends the run, by raising the stop signal once the windows'
+/// relative standard error meets the target after the minimum duration.
+fn sample_windows(
+    config: &BenchmarkConfig,
+    interval: Duration,
+    counters: &[IterationCounter],
+    start_signal: &AtomicBool,
+    stop_signal: &AtomicBool,
+) -> Vec<f64> {
+    let (_, _, bytes_per_iteration) = bytes_per_iteration(config);
+    let total = || -> usize { counters.iter().map(|c| c.0.load(Ordering::Relaxed)).sum() };
+
+    while !start_signal.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    let mut windows = Vec::new();
+    let start = Instant::now();
+    let mut window_start = start;
+    let mut window_iterations = total();
+    loop {
+        // Sleep in short slices so the end of the timed region is noticed promptly
+        while window_start.elapsed() < interval && !stop_signal.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(5).min(interval));
+        }
+        if stop_signal.load(Ordering::Relaxed) {
+            break;
+        }
+        let now = Instant::now();
+        let iterations = total();
+        windows.push(
+            ((iterations - window_iterations) * bytes_per_iteration) as f64
+                / (now - window_start).as_secs_f64()
+                / config.units.bytes(),
+        );
+        window_start = now;
+        window_iterations = iterations;
+
+        if let Some(auto) = config.auto_duration {
+            let precise =
+                relative_standard_error(&windows).is_some_and(|rse| rse < auto.target_rse_percent);
+            if precise && start.elapsed().as_secs_f64() >= config.duration_secs {
+                stop_signal.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+    windows
 }
 
-fn measure_memory_bandwidth(config: &BenchmarkConfig) -> (f64, f64, usize) {
+/// Redraws a status line with the elapsed and remaining time of the timed region until it
+/// ends, prefixed with `label` (the run's position in a sweep). Remaining time under
+/// --iterations is extrapolated from the iteration counts the workers publish.
+fn show_progress(
+    config: &BenchmarkConfig,
+    label: &str,
+    counters: &[IterationCounter],
+    start_signal: &AtomicBool,
+    stop_signal: &AtomicBool,
+) {
+    let mut line = ProgressLine::default();
+    line.update(&format!("{}setting up", label));
+    while !start_signal.load(Ordering::SeqCst) {
+        if stop_signal.load(Ordering::Relaxed) {
+            line.clear();
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let start = Instant::now();
+    while !stop_signal.load(Ordering::Relaxed) {
+        let elapsed = start.elapsed().as_secs_f64();
+        let remaining = match config.iterations {
+            Some(target) => {
+                let done: usize = counters.iter().map(|c| c.0.load(Ordering::Relaxed)).sum();
+                let total = target * config.thread_count;
+                (done > 0).then(|| elapsed * (total.saturating_sub(done)) as f64 / done as f64)
+            }
+            None => Some((config.run_limit_secs() - elapsed).max(0.0)),
+        };
+        let remaining = remaining.map_or("-".to_string(), |secs| format!("{:.0} s", secs));
+        line.update(&format!(
+            "{}{:.0} s elapsed, {} remaining",
+            label, elapsed, remaining
+        ));
+        // Sleep in short slices so the line is cleared promptly once the run ends
+        for _ in 0..20 {
+            if stop_signal.load(Ordering::Relaxed) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    line.clear();
+}
+
+/// How many times the configured run length the workers may go without completing a pass
+/// before the watchdog gives up on them
+const WATCHDOG_FACTOR: f64 = 5.0;
+/// Lower bound on the watchdog timeout, so very short runs are not cut off by one slow pass
+const WATCHDOG_MIN_SECS: f64 = 10.0;
+
+/// Aborts the process if the workers stop making progress: no worker completes a warmup or
+/// timed pass for `WATCHDOG_FACTOR` times the longer of the run and the warmup. Worker threads
+/// stuck inside a kernel cannot be cancelled, so exiting with a diagnostic is the only
+/// alternative to hanging. Returns once `done` is set after the workers have been joined.
+fn watchdog(
+    config: &BenchmarkConfig,
+    passes: &PassCounters,
+    start_signal: &AtomicBool,
+    done: &AtomicBool,
+) {
+    let run_secs = config
+        .run_limit_secs()
+        .max(config.warmup_secs.unwrap_or(0.0));
+    let timeout = Duration::from_secs_f64((run_secs * WATCHDOG_FACTOR).max(WATCHDOG_MIN_SECS));
+    if !stalled(passes, done, timeout) {
+        return;
+    }
+
+    // The start signal is only raised once every thread has finished warming up
+    let (phase, kernel) = if start_signal.load(Ordering::SeqCst) {
+        ("", &config.kernel)
+    } else {
+        (
+            "warmup ",
+            config.warmup_kernel.as_ref().unwrap_or(&config.kernel),
+        )
+    };
+    if progress_enabled() {
+        eprintln!();
+    }
+    eprintln!(
+        "No {}pass of the {:?} kernel completed in {:.0} s ({} threads, {} buffer, \
+         {}-byte stride, {:.1} s run), aborting",
+        phase,
+        kernel,
+        timeout.as_secs_f64(),
+        config.thread_count,
+        format_size(config.size as f64 / (1024.0 * 1024.0)),
+        config.stride * std::mem::size_of::<u32>(),
+        config.run_limit_secs()
+    );
+    eprintln!(
+        "A single pass over the buffer takes far longer than the run itself. Reduce \
+         --size, increase --stride or lengthen --duration."
+    );
+    std::process::exit(1);
+}
+
+/// Per-thread pass counts the watchdog reads: warmup passes, then timed passes. `setup_done`
+/// is raised once every worker has its buffers, so slow allocation and prefaulting are not
+/// mistaken for a stuck kernel.
+struct PassCounters {
+    setup_done: AtomicBool,
+    warmup: Vec<IterationCounter>,
+    timed: Arc<Vec<IterationCounter>>,
+}
+
+impl PassCounters {
+    fn total(&self) -> usize {
+        self.warmup
+            .iter()
+            .chain(self.timed.iter())
+            .map(|c| c.0.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+/// Waits for the workers to finish setup, then watches their pass counts until `done` is set.
+/// Returns whether `timeout` passed first without any pass completing.
+fn stalled(passes: &PassCounters, done: &AtomicBool, timeout: Duration) -> bool {
+    while !passes.setup_done.load(Ordering::SeqCst) {
+        if done.load(Ordering::Relaxed) {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut last_count = passes.total();
+    let mut last_progress = Instant::now();
+    while !done.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(10).min(timeout));
+        let count = passes.total();
+        if count != last_count {
+            last_count = count;
+            last_progress = Instant::now();
+        } else if last_progress.elapsed() >= timeout {
+            return true;
+        }
+    }
+    false
+}
+
+fn measure_memory_bandwidth(config: &BenchmarkConfig, progress: Option<&str>) -> Measurement {
+    let timestamp = timestamp_rfc3339();
     // Convert byte size to number of u32 elements
     let num_elements = config.size / std::mem::size_of::<u32>();
     let barrier = Arc::new(Barrier::new(config.thread_count));
-    let start_signal = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let start_signal = Arc::new(AtomicBool::new(false));
+    let stop_signal = Arc::new(AtomicBool::new(false));
+    let iteration_counters: Arc<Vec<IterationCounter>> = Arc::new(
+        (0..config.thread_count)
+            .map(|_| IterationCounter::default())
+            .collect(),
+    );
+
+    let sampler = config.sample_interval_ms.map(|ms| {
+        let config = config.clone();
+        let counters = Arc::clone(&iteration_counters);
+        let start_signal = Arc::clone(&start_signal);
+        let stop_signal = Arc::clone(&stop_signal);
+        thread::spawn(move || {
+            sample_windows(
+                &config,
+                Duration::from_millis(ms),
+                &counters,
+                &start_signal,
+                &stop_signal,
+            )
+        })
+    });
+
+    let workers_done = Arc::new(AtomicBool::new(false));
+    let passes = Arc::new(PassCounters {
+        setup_done: AtomicBool::new(false),
+        warmup: (0..config.thread_count)
+            .map(|_| IterationCounter::default())
+            .collect(),
+        timed: Arc::clone(&iteration_counters),
+    });
+    let watchdog = {
+        let config = config.clone();
+        let passes = Arc::clone(&passes);
+        let start_signal = Arc::clone(&start_signal);
+        let done = Arc::clone(&workers_done);
+        thread::spawn(move || watchdog(&config, &passes, &start_signal, &done))
+    };
+
+    let monitor = progress.map(|label| {
+        let config = config.clone();
+        let label = label.to_string();
+        let counters = Arc::clone(&iteration_counters);
+        let start_signal = Arc::clone(&start_signal);
+        let stop_signal = Arc::clone(&stop_signal);
+        thread::spawn(move || {
+            show_progress(&config, &label, &counters, &start_signal, &stop_signal)
+        })
+    });
 
     let mut handles = vec![];
     for thread_id in 0..config.thread_count {
         let barrier = Arc::clone(&barrier);
         let start_signal = Arc::clone(&start_signal);
+        let stop_signal = Arc::clone(&stop_signal);
+        let iteration_counters = Arc::clone(&iteration_counters);
+        let passes = Arc::clone(&passes);
         let core_ids = config.core_ids.clone();
         let kernel = config.kernel.clone();
+        let warmup_kernel = config.warmup_kernel.clone().unwrap_or(kernel.clone());
         let config = config.clone();
 
         let handle = thread::spawn(move || {
-            if !core_ids.is_empty() {
-                let core_id = core_ids[thread_id % core_ids.len()];
-                let _ = set_for_current(core_id);
-            }
+            let assigned_core =
+                (!core_ids.is_empty()).then(|| core_ids[thread_id % core_ids.len()]);
+            let pinned = assigned_core.is_some_and(set_for_current);
+            let placement = ThreadPlacement {
+                assigned_core: assigned_core.map(|core| core.id),
+                pinned,
+                observed_cpu: current_cpu(),
+            };
+
+            // Binding before the buffers are allocated and touched places all their pages
+            let mem_bind = config.mem_node.map_or(Ok(()), numa::bind_memory_to);
+            let realtime = config.rt_priority.map(sched::set_realtime_priority);
 
             // Each thread creates its own buffer
-            let mut rng = rand::rng();
-            let mut data: Vec<u32> = (0..num_elements).map(|_| rng.random()).collect();
+            let setup_start = Instant::now();
+            let mapped = match (config.memtype, &config.wc_path, &config.mmap_file) {
+                (MemType::Wc, Some(path), _) => {
+                    Some((path, Buffer::map_write_combining(path, num_elements)))
+                }
+                (_, _, Some(path)) => Some((path, Buffer::map_file(path, thread_id, num_elements))),
+                _ => None,
+            };
+            let mut data = match mapped {
+                Some((path, buffer)) => buffer.unwrap_or_else(|e| {
+                    eprintln!("Failed to map {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }),
+                None => Buffer::allocate(num_elements, config.alignment, config.hugepages),
+            };
+            let mut huge_pages = data.is_huge_pages();
+            // Device mappings are populated by the driver, and reading them back is slow. Write
+            // kernels overwrite the buffer, so only kernels that read it get filled, with or
+            // without prefaulting: the contents they read must not depend on --no-prefault.
+            if config.memtype == MemType::Normal {
+                if kernel.reads_source() {
+                    config.fill.apply(&mut data);
+                }
+                if config.prefault {
+                    prefault(&mut data);
+                }
+            }
+
+            // Copy needs a distinct destination so source and destination never share lines
+            let dst_len = if kernel.needs_destination() {
+                num_elements
+            } else {
+                0
+            };
+            let mut dst = Buffer::allocate(dst_len, config.alignment, config.hugepages);
+            if config.prefault {
+                prefault(&mut dst);
+            }
+            if kernel.needs_destination() {
+                huge_pages &= dst.is_huge_pages();
+            }
+            let setup = setup_start.elapsed();
 
-            // Wait for all threads to finish initialization
+            let params = KernelParams {
+                stride: config.stride,
+                prefetch_distance: config.prefetch_distance,
+                unroll: config.unroll,
+                tile: config.tile.unwrap_or(1),
+                block_len: config.block_len.unwrap_or(1),
+                row_len: config.row_len,
+                safe: config.safe,
+                offset: random_offset(config.stride),
+                stream_isa: detect_stream_isa(),
+            };
+
+            // Random access visits as many elements per pass as the strided kernels do, and the
+            // chase kernel's chains cover the same elements
+            let indices = match kernel {
+                Kernel::RandomRead => {
+                    random_indices(num_elements, num_elements.div_ceil(config.stride))
+                }
+                Kernel::MultiChase => {
+                    build_chains(&mut data, config.stride, config.chains.unwrap_or(1))
+                }
+                _ => Vec::new(),
+            };
+
+            // Opened per thread since perf events only count the thread that opened them
+            let counters = config.perf.then(PerfCounters::open);
+
+            // Wait for all threads to finish initialization. Unless --no-prefault is set every
+            // page is resident at this point, so neither warmup nor the timed region pays for
+            // demand page faults even with --warmup 0 or very short durations.
             barrier.wait();
+            if thread_id == 0 {
+                passes.setup_done.store(true, Ordering::SeqCst);
+            }
 
-            // Warmup
-            for _ in 0..config.warmup_iterations {
-                kernel.run(&mut data, config.stride);
+            // Warmup, publishing each pass so the watchdog also catches a stuck warmup
+            let mut warmup_passes = 0;
+            let mut warm_up = || {
+                warmup_kernel.run(&mut data, &mut dst, &params, &indices);
+                warmup_passes += 1;
+                passes.warmup[thread_id]
+                    .0
+                    .store(warmup_passes, Ordering::Relaxed);
+            };
+            match config.warmup_secs {
+                Some(warmup_secs) => {
+                    let warmup_start = Instant::now();
+                    while warmup_start.elapsed().as_secs_f64() < warmup_secs && !interrupted() {
+                        warm_up();
+                    }
+                }
+                None => {
+                    for _ in 0..config.warmup_iterations {
+                        if interrupted() {
+                            break;
+                        }
+                        warm_up();
+                    }
+                }
             }
 
-            // Wait for all threads to finish warmup
+            // Wait for all threads to finish warmup. With --no-prefault, any pages warmup did
+            // not touch are still faulted in on first access inside the timed region.
             barrier.wait();
 
-            // First thread sets the start signal
+            // First thread starts the clock and sets the start signal
+            let start = Instant::now();
             if thread_id == 0 {
-                start_signal.store(true, std::sync::atomic::Ordering::SeqCst);
+                start_signal.store(true, Ordering::SeqCst);
             }
 
             // Wait for start signal
-            while !start_signal.load(std::sync::atomic::Ordering::SeqCst) {
+            while !start_signal.load(Ordering::SeqCst) {
                 std::hint::spin_loop();
             }
 
+            if let Some(Ok(counters)) = &counters {
+                counters.start();
+            }
             let thread_start = Instant::now();
             let mut total_sum = 0u64;
             let mut iterations = 0usize;
+            let mut timings = config.record_timings.then(PassTimings::default);
+            let mut extended = false;
+
+            // With --iterations each thread stops after its own fixed number of passes. Otherwise
+            // all threads stop together once the first thread observes the duration has passed.
+            // Either way Ctrl-C stops early.
+            loop {
+                let done = match config.iterations {
+                    Some(target) => iterations >= target || interrupted(),
+                    None => stop_signal.load(Ordering::Relaxed),
+                };
+                if done {
+                    break;
+                }
 
-            while thread_start.elapsed().as_secs_f64() < config.duration_secs {
-                total_sum = total_sum.wrapping_add(kernel.run(&mut data, config.stride));
+                match &mut timings {
+                    Some(timings) => {
+                        let pass_start = Instant::now();
+                        let sum = kernel.run(&mut data, &mut dst, &params, &indices);
+                        timings.record(pass_start.elapsed().as_nanos() as u64);
+                        total_sum = total_sum.wrapping_add(sum);
+                    }
+                    None => {
+                        total_sum = total_sum
+                            .wrapping_add(kernel.run(&mut data, &mut dst, &params, &indices));
+                    }
+                }
                 iterations += 1;
+                // The watchdog, the sampler and the progress display read the published counts
+                iteration_counters[thread_id]
+                    .0
+                    .store(iterations, Ordering::Relaxed);
+
+                if thread_id == 0
+                    && config.iterations.is_none()
+                    && (start.elapsed().as_secs_f64() >= config.run_limit_secs() || interrupted())
+                {
+                    // Past the duration, a run short of --min-iterations keeps every thread
+                    // going so the extra passes fall in the same timed region
+                    let below_floor = config.min_iterations.is_some_and(|floor| {
+                        let total: usize = iteration_counters
+                            .iter()
+                            .map(|c| c.0.load(Ordering::Relaxed))
+                            .sum();
+                        total < floor
+                    });
+                    if below_floor && !interrupted() {
+                        extended = true;
+                    } else {
+                        stop_signal.store(true, Ordering::Relaxed);
+                    }
+                }
             }
 
             let thread_elapsed = thread_start.elapsed();
+            let perf = counters.map(|counters| counters.and_then(|c| c.stop()));
+
+            // Wait for every thread to finish its last iteration so the shared elapsed time
+            // covers all counted iterations
+            barrier.wait();
+            let shared_elapsed = (thread_id == 0).then(|| start.elapsed());
+            if thread_id == 0 {
+                // Fixed-iteration runs never raise the stop signal in the loop; the sampler
+                // still needs it
+                stop_signal.store(true, Ordering::Relaxed);
+            }
 
-            (total_sum as f64, iterations, thread_elapsed)
+            ThreadResult {
+                checksum: total_sum,
+                iterations,
+                elapsed: thread_elapsed,
+                shared_elapsed,
+                extended,
+                huge_pages,
+                setup,
+                perf,
+                mem_bind,
+                realtime,
+                timings,
+                placement,
+            }
         });
         handles.push(handle);
     }
 
-    let results: Vec<(f64, usize, Duration)> =
-        handles.into_iter().map(|h| h.join().unwrap()).collect();
+    let results: Vec<ThreadResult> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    workers_done.store(true, Ordering::Relaxed);
+    watchdog.join().unwrap();
+    let windows = sampler.map_or_else(Vec::new, |sampler| sampler.join().unwrap());
+    let timings = config.record_timings.then(|| {
+        let mut merged = PassTimings::default();
+        for timings in results.iter().filter_map(|r| r.timings.as_ref()) {
+            merged.merge(timings);
+        }
+        merged.histogram()
+    });
+    if let Some(monitor) = monitor {
+        monitor.join().unwrap();
+    }
 
-    // Use the maximum elapsed time across all threads
-    let elapsed = results
-        .iter()
-        .map(|(_, _, elapsed)| *elapsed)
-        .max()
-        .unwrap();
-    let total_sum: f64 = results.iter().map(|(sum, _, _)| sum).sum();
-    let total_iterations: usize = results.iter().map(|(_, iters, _)| *iters).sum();
-
-    // Calculate actual number of elements accessed with stride
-    let elements_per_iteration = std::cmp::min(
-        config.size / config.cpu_cache_info.l1d_line_size.unwrap_or(64),
-        (config.size / config.stride)
-            + if config.size % config.stride != 0 {
-                1
-            } else {
-                0
+    let huge_pages = results.iter().all(|r| r.huge_pages);
+    if config.hugepages && !huge_pages {
+        warning!("huge page allocation failed, falling back to regular pages");
+    }
+
+    if let (Some(node), Some(Err(e))) = (
+        config.mem_node,
+        results
+            .iter()
+            .map(|r| r.mem_bind.as_ref())
+            .find(|r| r.is_err()),
+    ) {
+        warning!(
+            "could not bind memory to NUMA node {} ({}), pages follow the default policy",
+            node,
+            e
+        );
+    }
+
+    // Workers that could not switch carry on at normal priority
+    let realtime =
+        config.rt_priority.is_some() && results.iter().all(|r| matches!(r.realtime, Some(Ok(()))));
+    if let (Some(priority), Some(Err(e))) = (
+        config.rt_priority,
+        results
+            .iter()
+            .filter_map(|r| r.realtime.as_ref())
+            .find(|r| r.is_err()),
+    ) {
+        let hint = if e.kind() == io::ErrorKind::PermissionDenied {
+            " (needs CAP_SYS_NICE or a sufficient RLIMIT_RTPRIO)"
+        } else {
+            ""
+        };
+        warning!(
+            "could not switch to SCHED_FIFO priority {} ({}){}, running at normal priority",
+            priority,
+            e,
+            hint
+        );
+    }
+
+    let perf = if config.perf {
+        let totals = results.iter().filter_map(|r| r.perf.as_ref()).try_fold(
+            None,
+            |acc: Option<PerfCounts>, counts| {
+                counts
+                    .as_ref()
+                    .map(|c| Some(acc.map_or(*c, |acc| acc.add(c))))
             },
-    );
+        );
+        match totals {
+            Ok(totals) => {
+                let missing = totals.map(|t| t.missing()).unwrap_or_default();
+                if !missing.is_empty() {
+                    warning!(
+                        "the PMU does not count {}, omitting {}",
+                        missing.join(" or "),
+                        if missing.len() == 1 { "it" } else { "them" }
+                    );
+                }
+                totals
+            }
+            Err(e) => {
+                warning!(
+                    "hardware performance counters unavailable ({}), omitting them; \
+                     check /proc/sys/kernel/perf_event_paranoid",
+                    e
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
 
-    // Calculate number of unique cache lines accessed
-    let cache_line_size = config.cpu_cache_info.l1d_line_size.unwrap_or(64);
+    // Single elapsed time shared by all threads, measured by the first thread
+    let elapsed = results[0].shared_elapsed.unwrap();
+    let total_iterations: usize = results.iter().map(|r| r.iterations).sum();
 
-    // Each access fetches exactly one cache line, regardless of stride
-    let bytes_processed = (elements_per_iteration * cache_line_size * total_iterations) as f64;
+    // Keep the kernels' results observable so their loads cannot be optimized away
+    std::hint::black_box(
+        results
+            .iter()
+            .fold(0u64, |acc, r| acc.wrapping_add(r.checksum)),
+    );
+
+    let (elements_per_iteration, cache_line_size, bytes_per_iteration) =
+        bytes_per_iteration(config);
+    let bytes_processed = (bytes_per_iteration * total_iterations) as f64;
     let seconds = elapsed.as_secs_f64();
-    let bandwidth = bytes_processed / seconds / (1024.0 * 1024.0 * 1024.0); // Convert to GiB/s
-
-    println!("\nBandwidth Calculation Details:");
-    println!("  Cache line size: {} bytes", cache_line_size);
-    println!("  Elements per iteration: {}", elements_per_iteration);
-    println!("  Total iterations: {}", total_iterations);
-    println!(
-        "  Total bytes processed: {:.2} GiB",
-        bytes_processed / (1024.0 * 1024.0 * 1024.0)
+    let units = config.units;
+    let bandwidth = bytes_processed / seconds / units.bytes();
+
+    // Each thread's bandwidth over its own iterations and elapsed time
+    let per_thread: Vec<f64> = results
+        .iter()
+        .map(|r| {
+            (bytes_per_iteration * r.iterations) as f64 / r.elapsed.as_secs_f64() / units.bytes()
+        })
+        .collect();
+
+    verbose!("\nBandwidth Calculation Details:");
+    verbose!("  Cache line size: {} bytes", cache_line_size);
+    verbose!("  Elements per iteration: {}", elements_per_iteration);
+    verbose!("  Accounting: {:?}", config.accounting);
+    verbose!("  Total iterations: {}", total_iterations);
+    verbose!(
+        "  Total bytes processed: {:.2} {}",
+        bytes_processed / units.bytes(),
+        units.amount()
     );
-    println!("  Elapsed time: {:.3} seconds", seconds);
+    verbose!("  Elapsed time: {:.3} seconds", seconds);
+    // Threads set up concurrently and start together, so the slowest one is what the run waited on
+    let alloc_ms = results
+        .iter()
+        .map(|r| r.setup.as_secs_f64() * 1000.0)
+        .fold(0.0, f64::max);
+    verbose!("  Allocation and fill (alloc_ms): {:.1} ms", alloc_ms);
     if config.thread_count > 1 {
-        println!(
+        verbose!(
             "  Average iterations per thread: {:.1}",
             total_iterations as f64 / config.thread_count as f64
         );
-        for (thread_id, (_, iters, _)) in results.iter().enumerate() {
-            println!("    Thread {}: {} iterations", thread_id, iters);
+        for (thread_id, (r, thread_bandwidth)) in results.iter().zip(&per_thread).enumerate() {
+            verbose!(
+                "    Thread {}: {} iterations, {:.2} {}, alloc {:.1} ms",
+                thread_id,
+                r.iterations,
+                thread_bandwidth,
+                units.label(),
+                r.setup.as_secs_f64() * 1000.0
+            );
+        }
+    }
+    let placement: Vec<ThreadPlacement> = results.iter().map(|r| r.placement.clone()).collect();
+    for (thread_id, placement) in placement.iter().enumerate() {
+        if let Some(core) = placement.assigned_core {
+            let observed = placement
+                .observed_cpu
+                .map_or("unknown".to_string(), |cpu| cpu.to_string());
+            verbose!(
+                "    Thread {}: core {} ({}), running on CPU {}",
+                thread_id,
+                core,
+                if placement.pinned {
+                    "pinned"
+                } else {
+                    "pinning failed"
+                },
+                observed
+            );
+        }
+    }
+    if !placement.iter().all(ThreadPlacement::honored) {
+        let failed = placement.iter().filter(|p| !p.honored()).count();
+        warning!(
+            "{} of {} threads are not on the core they were pinned to, so --affinity was not \
+             honored (a restricted cpuset?)",
+            failed,
+            placement.len()
+        );
+    }
+
+    if let Some(perf) = &perf {
+        let count = |count: Option<u64>| count.map_or("-".to_string(), |c| c.to_string());
+        verbose!(
+            "  Counters: {} LLC misses, {} instructions, {} cycles",
+            count(perf.cache_misses),
+            count(perf.instructions),
+            count(perf.cycles)
+        );
+    }
+    verbose!("  Bandwidth: {:.2} {}\n", bandwidth, units.label());
+
+    Measurement {
+        bandwidth,
+        elapsed_secs: seconds,
+        interrupted: interrupted(),
+        extended: results[0].extended,
+        realtime,
+        timestamp,
+        iterations: total_iterations,
+        huge_pages,
+        per_thread,
+        perf,
+        windows,
+        timings,
+        placement,
+    }
+}
+
+/// Applies `--config <file>` underneath the command line: options from the file go ahead of
+/// the real arguments, except those the command line sets or overrides with a conflicting flag
+fn expand_config_file(argv: Vec<String>) -> Result<Vec<String>, String> {
+    let position = argv
+        .iter()
+        .position(|arg| arg == "--config" || arg.starts_with("--config="));
+    let Some(position) = position else {
+        return Ok(argv);
+    };
+    let path = match argv[position].strip_prefix("--config=") {
+        Some(path) => path.to_string(),
+        None => argv
+            .get(position + 1)
+            .cloned()
+            .ok_or("--config requires a file")?,
+    };
+
+    ConfigFile::load(Path::new(&path))?.merge_under(&Args::command(), argv)
+}
+
+/// Prints everything the tool detects about the machine: cache geometry, the CPUs threads can
+/// be pinned to, SMT layout and NUMA nodes
+fn print_topology(
+    cache_info: &CacheInfo,
+    core_ids: &[CoreId],
+    topology: &Topology,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "Caches")?;
+    let levels = [
+        (
+            "L1D",
+            cache_info.l1d_size_kb,
+            cache_info.l1d_line_size,
+            cache_info.l1d_sets,
+            cache_info.l1d_associativity,
+        ),
+        (
+            "L2",
+            cache_info.l2_size_kb,
+            cache_info.l2_line_size,
+            cache_info.l2_sets,
+            cache_info.l2_associativity,
+        ),
+        (
+            "L3",
+            cache_info.l3_size_kb,
+            cache_info.l3_line_size,
+            cache_info.l3_sets,
+            cache_info.l3_associativity,
+        ),
+    ];
+    for (name, size, line, sets, assoc) in levels {
+        match size {
+            Some(size) => {
+                write!(out, "  {:<4} {} KB", name, size)?;
+                if let Some(line) = line {
+                    write!(out, ", {} B lines", line)?;
+                }
+                if let (Some(sets), Some(assoc)) = (sets, assoc) {
+                    write!(out, ", {} sets, {}-way", sets, assoc)?;
+                }
+                writeln!(out)?;
+            }
+            None => writeln!(out, "  {:<4} not detected", name)?,
         }
     }
-    println!("  Bandwidth: {:.2} GiB/s\n", bandwidth);
+    if let Some(entries) = cache_info.dtlb_4k_entries {
+        writeln!(out, "  DTLB {} entries (4 KiB pages)", entries)?;
+    }
+    if let Some(entries) = cache_info.dtlb_2m_entries {
+        writeln!(out, "  DTLB {} entries (2 MiB pages)", entries)?;
+    }
+    if let Some(entries) = cache_info.stlb_entries {
+        writeln!(out, "  STLB {} entries", entries)?;
+    }
+
+    let ids = |cores: &[CoreId]| cores.iter().map(|c| c.id).collect::<Vec<_>>();
+    writeln!(out, "\nCores")?;
+    writeln!(
+        out,
+        "  {} logical, {} physical",
+        topology.logical_cores, topology.physical_cores
+    )?;
+    writeln!(out, "  Available:      {:?}", ids(core_ids))?;
+    writeln!(
+        out,
+        "  Physical-only:  {:?}",
+        ids(&topology.primary_threads)
+    )?;
+
+    writeln!(out, "\nL3 domains")?;
+    let domains = l3_domains(core_ids);
+    if domains.is_empty() {
+        writeln!(out, "  not detected")?;
+    }
+    for (i, domain) in domains.iter().enumerate() {
+        writeln!(out, "  domain {}: CPUs {:?}", i, ids(domain))?;
+    }
+
+    writeln!(out, "\nNUMA nodes")?;
+    let nodes = numa::nodes();
+    if nodes.is_empty() {
+        writeln!(out, "  not detected")?;
+    }
+    for node in nodes {
+        writeln!(out, "  node {}: CPUs {:?}", node.id, node.cpus)?;
+    }
+    Ok(())
+}
 
-    (bandwidth, total_sum, total_iterations)
+/// What `--capabilities` reports, for scripts deciding which flags to pass
+#[derive(serde::Serialize)]
+struct Capabilities {
+    version: &'static str,
+    schema_version: u32,
+    arch: &'static str,
+    os: &'static str,
+    /// ISA extensions the kernels can use, as detected at run time (empty off x86-64)
+    cpu_features: BTreeMap<&'static str, bool>,
+    /// Optional functionality available on this platform
+    features: BTreeMap<&'static str, bool>,
+    formats: Vec<&'static str>,
+    operations: Vec<String>,
+    dtypes: Vec<String>,
+}
+
+fn print_capabilities(out: &mut dyn Write) -> io::Result<()> {
+    #[cfg(target_arch = "x86_64")]
+    let cpu_features = BTreeMap::from([
+        ("sse4.1", is_x86_feature_detected!("sse4.1")),
+        ("avx", is_x86_feature_detected!("avx")),
+        ("avx2", is_x86_feature_detected!("avx2")),
+        ("avx512f", is_x86_feature_detected!("avx512f")),
+        ("avx512bw", is_x86_feature_detected!("avx512bw")),
+    ]);
+    #[cfg(not(target_arch = "x86_64"))]
+    let cpu_features = BTreeMap::new();
+
+    let linux = cfg!(target_os = "linux");
+    let features = BTreeMap::from([
+        ("hugepages", linux),
+        ("mmap_file", linux),
+        ("numa", linux),
+        ("perf", linux),
+        ("realtime", linux),
+        ("streaming_loads", cfg!(target_arch = "x86_64")),
+        ("write_combining", linux),
+    ]);
+
+    let capabilities = Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        schema_version: SCHEMA_VERSION,
+        arch: std::env::consts::ARCH,
+        os: std::env::consts::OS,
+        cpu_features,
+        features,
+        formats: FORMATS.to_vec(),
+        // readwrite is accepted by the parser but not implemented
+        operations: Operation::value_variants()
+            .iter()
+            .filter(|op| !matches!(op, Operation::ReadWrite))
+            .filter_map(|op| op.to_possible_value())
+            .map(|op| op.get_name().to_string())
+            .collect(),
+        dtypes: Dtype::value_variants()
+            .iter()
+            .filter_map(|d| d.to_possible_value())
+            .map(|d| d.get_name().to_string())
+            .collect(),
+    };
+    writeln!(
+        out,
+        "{}",
+        serde_json::to_string_pretty(&capabilities).unwrap()
+    )
 }
 
 fn main() {
-    let args = Args::parse();
+    let argv = expand_config_file(std::env::args().collect()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let args = Args::parse_from(argv);
+
+    set_verbosity(if args.quiet {
+        Verbosity::Quiet
+    } else if args.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    });
+
+    // Ctrl-C stops the current run early and still reports what was measured
+    install_interrupt_handler();
 
     // Get available CPU cores
     let core_ids = get_core_ids().unwrap_or_default();
-    let available_cores = core_ids.len();
+    let topology = detect_topology(&core_ids);
 
     // Get CPU cache information
     let cache_info = get_cpu_info();
 
+    if args.capabilities {
+        if let Err(e) = print_capabilities(&mut io::stdout()) {
+            eprintln!("Failed to write capabilities: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.list_topology {
+        if let Err(e) = print_topology(&cache_info, &core_ids, &topology, &mut io::stdout()) {
+            eprintln!("Failed to write topology: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let (core_ids, available_cores) = if args.physical_cores_only {
+        (topology.primary_threads.clone(), topology.physical_cores)
+    } else {
+        (core_ids, topology.logical_cores)
+    };
+
     // Use cache line size by default, or user-specified stride if provided
     let stride_bytes = args
         .stride
         .unwrap_or_else(|| cache_info.l1d_line_size.unwrap_or(64));
 
+    let alignment = args
+        .align
+        .unwrap_or_else(|| cache_info.l1d_line_size.unwrap_or(64));
+
     // Convert byte stride to element stride
     let stride = stride_bytes.div_ceil(std::mem::size_of::<u32>());
 
     // Print CPU cache information
-    println!("CPU Cache Information:");
+    verbose!("CPU Cache Information:");
     if let Some(size) = cache_info.l1d_size_kb {
-        println!(
+        verbose!(
             "L1D Cache: {} KB (line size: {} bytes)",
             size,
             cache_info.l1d_line_size.unwrap_or(0)
         );
         if let (Some(sets), Some(assoc)) = (cache_info.l1d_sets, cache_info.l1d_associativity) {
-            println!("         Sets: {}, Associativity: {}-way", sets, assoc);
+            verbose!("         Sets: {}, Associativity: {}-way", sets, assoc);
         }
     }
     if let Some(size) = cache_info.l2_size_kb {
-        println!(
+        verbose!(
             "L2 Cache:  {} KB (line size: {} bytes)",
             size,
             cache_info.l2_line_size.unwrap_or(0)
         );
         if let (Some(sets), Some(assoc)) = (cache_info.l2_sets, cache_info.l2_associativity) {
-            println!("         Sets: {}, Associativity: {}-way", sets, assoc);
+            verbose!("         Sets: {}, Associativity: {}-way", sets, assoc);
         }
     }
     if let Some(size) = cache_info.l3_size_kb {
-        println!(
+        verbose!(
             "L3 Cache:  {} KB (line size: {} bytes)",
             size,
             cache_info.l3_line_size.unwrap_or(0)
         );
         if let (Some(sets), Some(assoc)) = (cache_info.l3_sets, cache_info.l3_associativity) {
-            println!("         Sets: {}, Associativity: {}-way", sets, assoc);
+            verbose!("         Sets: {}, Associativity: {}-way", sets, assoc);
         }
     }
-    println!();
+    if let Some(entries) = cache_info.dtlb_4k_entries {
+        verbose!("L1 DTLB:   {} entries (4 KiB pages)", entries);
+    }
+    if let Some(entries) = cache_info.dtlb_2m_entries {
+        verbose!("L1 DTLB:   {} entries (2 MiB pages)", entries);
+    }
+    if let Some(entries) = cache_info.stlb_entries {
+        verbose!("L2 STLB:   {} entries", entries);
+    }
+    verbose!(
+        "Cores:     {} physical, {} logical",
+        topology.physical_cores,
+        topology.logical_cores
+    );
+    verbose!();
 
     // Convert MiB to bytes (not number of elements)
-    let size = args.size * 1024 * 1024;
-    let size_mib = size as f64 / (1024.0 * 1024.0);
+    let size = match args.resident {
+        Some(kb) => kb.checked_mul(1024).unwrap_or_else(|| {
+            eprintln!("--resident of {} KiB does not fit in the address space", kb);
+            std::process::exit(1);
+        }),
+        None => args.size * 1024 * 1024,
+    };
+
+    let kernel = match (args.operation, args.simd, args.random) {
+        (Operation::Read, true, false) if args.dtype == Dtype::U64 => Kernel::SimdReadU64,
+        (Operation::Write, true, false) if args.dtype == Dtype::U64 => Kernel::SimdWriteU64,
+        (Operation::Read, true, false) if args.dtype == Dtype::F32 => Kernel::SimdReadF32,
+        (Operation::Write, true, false) if args.dtype == Dtype::F32 => Kernel::SimdWriteF32,
+        _ if args.dtype != Dtype::U32 => {
+            eprintln!(
+                "--dtype {} is only supported for the SIMD read and write operations",
+                args.dtype.to_possible_value().unwrap().get_name()
+            );
+            std::process::exit(1);
+        }
+        (Operation::Read, false, false) if args.tile.is_some() => Kernel::Tiled2D,
+        _ if args.tile.is_some() => {
+            eprintln!("--tile is only supported for the scalar read operation");
+            std::process::exit(1);
+        }
+        (Operation::Read, false, false) if args.block_len.is_some() => Kernel::BlockRead,
+        _ if args.block_len.is_some() => {
+            eprintln!("--block-len is only supported for the scalar read operation");
+            std::process::exit(1);
+        }
+        (Operation::Read, false, false) if args.chains.is_some() => Kernel::MultiChase,
+        _ if args.chains.is_some() => {
+            eprintln!("--chains is only supported for the scalar read operation");
+            std::process::exit(1);
+        }
+        (Operation::Read, _, false) if args.streaming => Kernel::StreamRead,
+        _ if args.streaming => {
+            eprintln!("--streaming is only supported for the strided read operation");
+            std::process::exit(1);
+        }
+        (Operation::Read, _, true) => Kernel::RandomRead,
+        (Operation::Read, false, false) => Kernel::ScalarRead,
+        (Operation::Read, true, false) => Kernel::SimdRead,
+        (Operation::Write, false, false) => Kernel::ScalarWrite,
+        (Operation::Write, true, false) => Kernel::SimdWrite,
+        (Operation::Update, false, false) => Kernel::ScalarUpdate,
+        (Operation::Update, true, false) => Kernel::SimdUpdate,
+        (Operation::Copy, false, false) => Kernel::Copy,
+        (Operation::Copy, true, false) => {
+            eprintln!("SIMD is not supported for the copy operation");
+            std::process::exit(1);
+        }
+        (Operation::Bidir, false, false) => Kernel::Bidir,
+        (Operation::Bidir, true, false) => {
+            eprintln!("SIMD is not supported for the bidir operation");
+            std::process::exit(1);
+        }
+        (Operation::ReadWrite, _, _) => {
+            eprintln!("ReadWrite operation not yet implemented");
+            std::process::exit(1);
+        }
+        (_, _, true) => {
+            eprintln!("Random access is only supported for the read operation");
+            std::process::exit(1);
+        }
+    };
 
-    let kernel = match (args.operation, args.simd) {
+    // Only single-buffer kernels can stand in for the measured one during warmup
+    let warmup_kernel = args.warmup_kernel.as_ref().map(|op| match (op, args.simd) {
         (Operation::Read, false) => Kernel::ScalarRead,
         (Operation::Read, true) => Kernel::SimdRead,
         (Operation::Write, false) => Kernel::ScalarWrite,
         (Operation::Write, true) => Kernel::SimdWrite,
-        (Operation::ReadWrite, _) => {
-            println!("ReadWrite operation not yet implemented");
+        (Operation::Update, false) => Kernel::ScalarUpdate,
+        (Operation::Update, true) => Kernel::SimdUpdate,
+        (Operation::ReadWrite | Operation::Copy | Operation::Bidir, _) => {
+            eprintln!("--warmup-kernel only supports the read, write and update operations");
             std::process::exit(1);
         }
-    };
+    });
+    // The chase kernel's chains are stored in the buffer, which writing would scramble
+    if kernel == Kernel::MultiChase
+        && warmup_kernel
+            .as_ref()
+            .is_some_and(|k| k != &Kernel::ScalarRead)
+    {
+        eprintln!("--chains can only be warmed up with the read operation");
+        std::process::exit(1);
+    }
+
+    if args.memtype == MemType::Wc {
+        // Reads from write-combining memory are uncached, so only writes are meaningful
+        if kernel.reads_source() {
+            eprintln!("--memtype wc is only supported for the write operation");
+            std::process::exit(1);
+        }
+        // Fail before any run rather than in every worker thread
+        let path = args.wc_path.as_deref().unwrap();
+        if let Err(e) = Buffer::map_write_combining(path, size / std::mem::size_of::<u32>()) {
+            let hint = if e.kind() == io::ErrorKind::PermissionDenied {
+                " (mapping device memory usually needs root)"
+            } else {
+                ""
+            };
+            eprintln!(
+                "Failed to map {} as write-combining memory: {}{}",
+                path.display(),
+                e,
+                hint
+            );
+            std::process::exit(1);
+        }
+    }
+
+    if args.prefetch > 0 && kernel != Kernel::ScalarRead {
+        warning!("--prefetch only applies to the scalar read kernel and is ignored");
+    }
 
     let thread_count = if args.parallel {
         args.threads.unwrap_or(available_cores)
@@ -257,40 +1771,813 @@ fn main() {
         1
     };
 
+    // Spec sheets quote GB/s, convert to the units results are reported in
+    let peak = args
+        .peak_gbps
+        .or_else(detect_peak_gbps)
+        .map(|gbps| gbps * 1e9 / args.units.bytes());
+
+    // Each run is an optional cache level label plus the buffer size in bytes
+    let sizes: Vec<(Option<String>, usize)> = if args.levels {
+        let working_sets = cache_info.level_working_sets().unwrap_or_else(|| {
+            info!("Cache information unavailable, using fixed size ladder for levels\n");
+            FALLBACK_LEVEL_SIZES.to_vec()
+        });
+        working_sets
+            .into_iter()
+            .map(|(level, size)| (Some(level.to_string()), size))
+            .collect()
+    } else if args.resident.is_some() {
+        match cache_info.l3_size_kb {
+            Some(l3_kb) if size >= l3_kb * 1024 => warning!(
+                "--resident working set of {} is not smaller than L3 ({} KiB) and will spill to DRAM",
+                format_size(size as f64 / (1024.0 * 1024.0)),
+                l3_kb
+            ),
+            Some(_) => {}
+            None => warning!("L3 size unknown, cannot verify the --resident working set fits"),
+        }
+        let level = cache_info.fitting_level(size);
+        if let Some(level) = level.filter(|&level| level != "DRAM") {
+            info!(
+                "Resident working set of {} fits in {}\n",
+                format_size(size as f64 / (1024.0 * 1024.0)),
+                level
+            );
+        }
+        vec![(level.map(str::to_string), size)]
+    } else {
+        vec![(None, size)]
+    };
+
+    // Every size is measured at every stride, converting each byte stride to elements
+    let strides = match &args.stride_sweep {
+        Some(sweep) => sweep.strides(),
+        None => vec![stride_bytes],
+    };
+
+    // --numa-matrix repeats every run for each (CPU node, memory node) pair
+    let numa_nodes = if args.numa_matrix {
+        numa::nodes()
+    } else {
+        Vec::new()
+    };
+    if args.numa_matrix && numa_nodes.is_empty() {
+        eprintln!("--numa-matrix requires NUMA topology from /sys/devices/system/node");
+        std::process::exit(1);
+    }
+    let placements: Vec<Option<(usize, usize)>> = if args.numa_matrix {
+        numa_nodes
+            .iter()
+            .flat_map(|cpu| numa_nodes.iter().map(|mem| Some((cpu.id, mem.id))))
+            .collect()
+    } else {
+        vec![None]
+    };
+
+    // --ccd-placement repeats every run packed onto one L3 domain and spread across them all
+    let (packed_cores, spread_cores) = if args.ccd_placement {
+        let domains = l3_domains(&core_ids);
+        if domains.len() < 2 {
+            eprintln!(
+                "--ccd-placement needs at least two L3 domains, found {}",
+                domains.len()
+            );
+            std::process::exit(1);
+        }
+        if thread_count > domains[0].len() {
+            warning!(
+                "{} threads do not fit on the {} cores of one L3 domain, packed threads share cores",
+                thread_count,
+                domains[0].len()
+            );
+        }
+        // Deal the cores out domain by domain so consecutive threads land on different CCDs
+        let longest = domains.iter().map(Vec::len).max().unwrap_or(0);
+        let spread: Vec<CoreId> = (0..longest)
+            .flat_map(|i| {
+                domains
+                    .iter()
+                    .filter_map(move |domain| domain.get(i).copied())
+            })
+            .collect();
+        (domains[0].clone(), spread)
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    let layouts: Vec<Option<CcdPlacement>> = if args.ccd_placement {
+        vec![Some(CcdPlacement::Packed), Some(CcdPlacement::Spread)]
+    } else {
+        vec![None]
+    };
+
+    // --compare runs each kernel of the set back to back at every point
+    let kernels = if args.compare_kernels {
+        COMPARE_KERNELS.to_vec()
+    } else {
+        vec![kernel.clone()]
+    };
+
+    let mut runs = Vec::new();
+    for (level, size) in &sizes {
+        for &stride_bytes in &strides {
+            for &numa in &placements {
+                for &ccd in &layouts {
+                    for kernel in &kernels {
+                        runs.push(Run {
+                            level: level.clone(),
+                            size: *size,
+                            stride_bytes,
+                            numa,
+                            ccd,
+                            kernel: kernel.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(path) = &args.mmap_file {
+        // Mapping the last thread's region up front creates and sizes the file once, so the
+        // workers only map it and failures surface before any run
+        let largest = runs.iter().map(|run| run.size).max().unwrap_or(size);
+        let last = thread_count - 1;
+        if let Err(e) = Buffer::map_file(path, last, largest / std::mem::size_of::<u32>()) {
+            eprintln!("Failed to map {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+
     let config = BenchmarkConfig {
         size,
         stride,
+        prefetch_distance: args.prefetch,
+        unroll: args.unroll,
+        tile: args.tile.map(NonZeroUsize::get),
+        row_len: args.row_len.get(),
+        block_len: args.block_len.map(NonZeroUsize::get),
+        safe: args.safe,
+        channels: args.channels.map(|n| n as usize),
+        chains: args.chains,
+        sample_interval_ms: args
+            .sample_interval
+            .or((args.steady_state || args.auto_duration).then_some(DEFAULT_SAMPLE_INTERVAL_MS)),
+        record_timings: args.record_timings,
+        auto_duration: args.auto_duration.then_some(AutoDuration {
+            target_rse_percent: args.target_rse,
+            max_secs: args.max_duration.max(args.duration),
+        }),
+        steady_state: args.steady_state.then_some(SteadyCriterion {
+            windows: args.steady_windows as usize,
+            cv_percent: args.steady_cv,
+        }),
+        alignment,
+        hugepages: args.hugepages,
+        prefault: !args.no_prefault,
+        fill: args.fill,
+        memtype: args.memtype,
+        wc_path: args.wc_path.clone(),
+        mmap_file: args.mmap_file.clone(),
         duration_secs: args.duration,
+        iterations: args.iterations.map(|n| n as usize),
+        min_iterations: args.min_iterations.map(|n| n as usize),
         warmup_iterations: args.warmup,
+        warmup_secs: args.warmup_secs,
+        warmup_kernel,
         kernel: kernel.clone(),
         thread_count,
         core_ids: if args.affinity { core_ids } else { vec![] },
         cpu_cache_info: cache_info.clone(),
+        units: args.units,
+        accounting: args.accounting,
+        perf: args.perf,
+        rt_priority: args.rt_priority,
+        cpu_node: None,
+        mem_node: None,
+        ccd_placement: None,
+    };
+
+    let run_config = |run: &Run| {
+        let mut run_config = BenchmarkConfig {
+            size: run.size,
+            stride: run.stride_bytes.div_ceil(std::mem::size_of::<u32>()),
+            kernel: run.kernel.clone(),
+            ..config.clone()
+        };
+        if let Some((cpu_node, mem_node)) = run.numa {
+            let cpus = &numa_nodes.iter().find(|n| n.id == cpu_node).unwrap().cpus;
+            run_config.core_ids = cpus.iter().map(|&id| CoreId { id }).collect();
+            run_config.cpu_node = Some(cpu_node);
+            run_config.mem_node = Some(mem_node);
+        }
+        if let Some(ccd) = run.ccd {
+            run_config.core_ids = match ccd {
+                CcdPlacement::Packed => packed_cores.clone(),
+                CcdPlacement::Spread => spread_cores.clone(),
+            };
+            run_config.ccd_placement = Some(ccd);
+        }
+        run_config
     };
 
     let mut benchmark_results = BenchmarkResults {
+        schema_version: SCHEMA_VERSION,
         results: Vec::new(),
         config: config.clone(),
+        machine: get_machine_info(&topology),
+        summary: Summary::default(),
+        groups: None,
     };
 
     if args.parallel {
-        println!("Using parallel measurement with {:?} kernel", kernel);
+        verbose!("Using parallel measurement with {:?} kernel", kernel);
     } else {
-        println!("Using single-threaded measurement with {:?} kernel", kernel);
+        verbose!("Using single-threaded measurement with {:?} kernel", kernel);
     }
 
-    let (bandwidth, _sum, iterations) = measure_memory_bandwidth(&config);
+    if let Some(block_len) = args.block_len {
+        let too_short = runs
+            .iter()
+            .find(|run| run.stride_bytes.div_ceil(std::mem::size_of::<u32>()) < block_len.get());
+        if let Some(Run { stride_bytes, .. }) = too_short {
+            eprintln!(
+                "--block-len of {} elements is longer than the {} B stride",
+                block_len, stride_bytes
+            );
+            std::process::exit(1);
+        }
+    }
 
-    benchmark_results.results.push(BenchmarkResult {
-        size_mib,
-        bandwidth_gib_s: bandwidth,
-        simd_enabled: matches!(config.kernel, Kernel::SimdRead | Kernel::SimdWrite),
-        parallel_enabled: config.thread_count > 1,
-        affinity_enabled: !config.core_ids.is_empty(),
-        iterations,
-        warmup_iterations: config.warmup_iterations,
-        threads: config.thread_count,
-    });
+    if let Some(chains) = args.chains {
+        let too_small = runs.iter().find(|run| {
+            let stride = run.stride_bytes.div_ceil(std::mem::size_of::<u32>());
+            Kernel::chase_steps(run.size / std::mem::size_of::<u32>(), stride, chains) == 0
+        });
+        if let Some(Run { size, .. }) = too_small {
+            eprintln!(
+                "A {} buffer has fewer strided elements than the {} requested chains",
+                format_size(*size as f64 / (1024.0 * 1024.0)),
+                chains
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("Failed to create {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(io::stdout()),
+    };
+
+    if args.dry_run {
+        let plan: Vec<(Option<String>, BenchmarkConfig)> = runs
+            .iter()
+            .map(|run| (run.level.clone(), run_config(run)))
+            .collect();
+        if let Err(e) = print_plan(&plan, &args.format, &mut out) {
+            eprintln!("Failed to write plan: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let show_progress = progress_enabled();
+    for (index, run) in runs.iter().enumerate() {
+        if interrupted() {
+            break;
+        }
+
+        let run_config = run_config(run);
+        let (level, size, stride_bytes) = (run.level.clone(), run.size, run.stride_bytes);
+
+        if let Some(level) = &level {
+            verbose!(
+                "\nMeasuring {} bandwidth with {} buffer",
+                level,
+                format_size(size as f64 / (1024.0 * 1024.0))
+            );
+        }
+
+        let label = if runs.len() > 1 {
+            format!("[{}/{}] ", index + 1, runs.len())
+        } else {
+            String::new()
+        };
+        let measurement =
+            measure_memory_bandwidth(&run_config, show_progress.then_some(label.as_str()));
+        info!(
+            "{:?} {}, {} B stride, {} thread(s): {:.2} {} over {:.2} s ({} iterations)",
+            run_config.kernel,
+            format_size(size as f64 / (1024.0 * 1024.0)),
+            stride_bytes,
+            run_config.thread_count,
+            measurement.bandwidth,
+            run_config.units.label(),
+            measurement.elapsed_secs,
+            measurement.iterations
+        );
+
+        if measurement.extended {
+            info!(
+                "Extended to {:.2} s to reach {} iterations",
+                measurement.elapsed_secs,
+                run_config.min_iterations.unwrap_or_default()
+            );
+        }
+
+        // A far overrun means single passes were long enough to dominate the timed region.
+        // Auto-extended runs and runs extended for --min-iterations are meant to outlast the
+        // requested duration.
+        let mut reliable = !measurement.interrupted;
+        if reliable
+            && run_config.iterations.is_none()
+            && run_config.auto_duration.is_none()
+            && !measurement.extended
+        {
+            let deviation = (measurement.elapsed_secs - run_config.duration_secs).abs()
+                / run_config.duration_secs
+                * 100.0;
+            if deviation > args.tolerance {
+                warning!(
+                    "measured {:.2} s against the requested {:.2} s ({:.0}% off), the result may be unreliable",
+                    measurement.elapsed_secs,
+                    run_config.duration_secs,
+                    deviation
+                );
+                reliable = false;
+            }
+        }
+        if reliable && measurement.iterations < MIN_RELIABLE_ITERATIONS {
+            warning!(
+                "only {} iterations completed (fewer than {}), the result may be unreliable",
+                measurement.iterations,
+                MIN_RELIABLE_ITERATIONS
+            );
+            reliable = false;
+        }
+
+        let rse = run_config
+            .auto_duration
+            .and_then(|_| relative_standard_error(&measurement.windows));
+        if let Some(auto) = run_config.auto_duration {
+            if rse.is_none_or(|rse| rse >= auto.target_rse_percent) && !measurement.interrupted {
+                warning!(
+                    "relative standard error did not reach {:.2}% within {:.1} s",
+                    auto.target_rse_percent,
+                    auto.max_secs
+                );
+                reliable = false;
+            }
+        }
+
+        // Under --steady-state the windows after steady state stand in for the whole region
+        let steady = run_config
+            .steady_state
+            .and_then(|criterion| steady_state(&measurement.windows, &criterion));
+        if run_config.steady_state.is_some() && steady.is_none() {
+            warning!(
+                "bandwidth never reached steady state over {} windows, reporting the whole run",
+                measurement.windows.len()
+            );
+            reliable = false;
+        }
+        let bandwidth = steady.map_or(measurement.bandwidth, |steady| steady.mean);
+        // Each bidir stream's share of the bandwidth is its share of the bytes a pass moves
+        let bidir_split = (run_config.kernel == Kernel::Bidir).then(|| {
+            let (read, write) = bidir_stream_bytes(&run_config);
+            let total = (read + write) as f64;
+            (
+                bandwidth * read as f64 / total,
+                bandwidth * write as f64 / total,
+            )
+        });
+
+        let result = BenchmarkResult {
+            size_mib: size as f64 / (1024.0 * 1024.0),
+            stride_bytes,
+            bandwidth_gib_s: bandwidth,
+            units: run_config.units,
+            simd_enabled: run_config.kernel.is_simd(),
+            parallel_enabled: run_config.thread_count > 1,
+            affinity_enabled: !run_config.core_ids.is_empty(),
+            hugepages_enabled: measurement.huge_pages,
+            iterations: measurement.iterations,
+            warmup_iterations: run_config.warmup_iterations,
+            threads: run_config.thread_count,
+            level,
+            per_thread: measurement.per_thread,
+            elapsed_secs: measurement.elapsed_secs,
+            interrupted: measurement.interrupted,
+            reliable,
+            timestamp: measurement.timestamp,
+            peak_gib_s: peak,
+            efficiency_percent: peak.map(|peak| bandwidth / peak * 100.0),
+            cache_misses_per_iteration: measurement
+                .perf
+                .and_then(|p| p.cache_misses)
+                .map(|misses| misses as f64 / measurement.iterations as f64),
+            ipc: measurement.perf.and_then(|p| p.ipc()),
+            latency_ns: (run_config.kernel == Kernel::MultiChase).then(|| {
+                let steps = Kernel::chase_steps(
+                    size / std::mem::size_of::<u32>(),
+                    run_config.stride,
+                    run_config.chains.unwrap_or(1),
+                );
+                let passes_per_thread =
+                    measurement.iterations as f64 / run_config.thread_count as f64;
+                measurement.elapsed_secs * 1e9 / (passes_per_thread * steps as f64)
+            }),
+            channels: run_config.channels,
+            bandwidth_per_channel: run_config
+                .channels
+                .map(|channels| bandwidth / channels as f64),
+            ops_per_byte: run_config.kernel.ops_per_byte(),
+            cpu_node: run_config.cpu_node,
+            mem_node: run_config.mem_node,
+            ccd_placement: run_config.ccd_placement,
+            realtime: measurement.realtime,
+            affinity_ok: measurement.placement.iter().all(ThreadPlacement::honored),
+            thread_placement: measurement.placement,
+            kernel_name: format!("{:?}", run_config.kernel),
+            rse_percent: rse,
+            pass_timings: measurement.timings,
+            read_bandwidth: bidir_split.map(|(read, _)| read),
+            write_bandwidth: bidir_split.map(|(_, write)| write),
+            ci95_low: steady.map(|steady| steady.ci95_low),
+            ci95_high: steady.map(|steady| steady.ci95_high),
+            discarded_windows: steady.map(|steady| steady.discarded),
+            accounting: run_config.accounting,
+            line_bytes_per_iteration: line_bytes_per_iteration(&run_config).2,
+            referenced_bytes_per_iteration: referenced_bytes_per_iteration(&run_config),
+            throttled: throttled(&measurement.windows, args.throttle_threshold),
+            bandwidth_windows: measurement.windows,
+        };
 
-    print_results(&benchmark_results, &args.format);
+        if args.format == "ndjson" {
+            let line = ndjson_line(&result, &run_config);
+            if let Err(e) = writeln!(out, "{}", line).and_then(|_| out.flush()) {
+                eprintln!("Failed to write results: {}", e);
+                std::process::exit(1);
+            }
+        }
+        benchmark_results.results.push(result);
+    }
+
+    benchmark_results.summary = Summary::of(&benchmark_results.results);
+    if args.group_results {
+        benchmark_results.groups = Some(group_results(&benchmark_results.results));
+    }
+
+    // ndjson lines were already streamed as each run finished
+    if args.format != "ndjson" {
+        if let Err(e) = print_results(
+            &benchmark_results,
+            &args.format,
+            !args.csv_no_header,
+            &mut out,
+        ) {
+            eprintln!("Failed to write results: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(path) = &args.append {
+        if let Err(e) = append_csv(&benchmark_results, path, !args.csv_no_header) {
+            eprintln!("Failed to append results to {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(path) = &args.baseline {
+        let baseline = load_baseline(path).unwrap_or_else(|e| {
+            eprintln!("Failed to load baseline {}: {}", path.display(), e);
+            std::process::exit(1);
+        });
+        let comparisons = compare(&baseline, &benchmark_results);
+        // The table goes with the text report; structured output keeps it on stderr so the
+        // results stay parseable
+        let printed = if args.format == "text" {
+            print_comparison(&comparisons, &mut out)
+        } else {
+            print_comparison(&comparisons, &mut io::stderr())
+        };
+        if let Err(e) = printed {
+            eprintln!("Failed to write the baseline comparison: {}", e);
+            std::process::exit(1);
+        }
+
+        if let Some(threshold) = args.fail_under {
+            // Nothing to check is a failure, not a pass: the baseline is for another setup
+            if comparisons.is_empty() {
+                eprintln!(
+                    "\nNo results match the baseline {}, so --fail-under cannot pass",
+                    path.display()
+                );
+                std::process::exit(1);
+            }
+            let regressions: Vec<_> = comparisons
+                .iter()
+                .filter(|c| c.delta_percent() < -threshold)
+                .collect();
+            if !regressions.is_empty() {
+                eprintln!(
+                    "\n{} result(s) regressed by more than {:.1}% against the baseline",
+                    regressions.len(),
+                    threshold
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(min_bandwidth) = args.min_bandwidth {
+        let below: Vec<_> = benchmark_results
+            .results
+            .iter()
+            .filter(|r| r.bandwidth_gib_s < min_bandwidth)
+            .collect();
+        for result in &below {
+            eprintln!(
+                "{} with {} thread(s): {:.2} {} is below the minimum of {:.2} {}",
+                format_size(result.size_mib),
+                result.threads,
+                result.bandwidth_gib_s,
+                args.units.label(),
+                min_bandwidth,
+                args.units.label()
+            );
+        }
+        if !below.is_empty() {
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A config for `kernel` over `size` bytes at an element `stride`, with 64-byte lines
+    fn config(
+        kernel: Kernel,
+        size: usize,
+        stride: usize,
+        accounting: Accounting,
+    ) -> BenchmarkConfig {
+        let mut config: BenchmarkConfig = serde_json::from_value(json!({
+            "size": size,
+            "stride": stride,
+            "prefetch_distance": 0,
+            "unroll": 1,
+            "alignment": 64,
+            "hugepages": false,
+            "prefault": true,
+            "duration_secs": 1.0,
+            "warmup_iterations": 0,
+            "kernel": "ScalarRead",
+            "thread_count": 1,
+            "cpu_cache_info": {"l1d_line_size": 64},
+        }))
+        .unwrap();
+        config.kernel = kernel;
+        config.accounting = accounting;
+        config
+    }
+
+    #[test]
+    fn bidir_streams_add_up_to_the_bytes_per_pass() {
+        for accounting in [Accounting::Lines, Accounting::Bytes] {
+            for (size, stride) in [(4096, 1), (4096, 16), (4100, 16), (1 << 20, 3), (64, 1024)] {
+                let config = config(Kernel::Bidir, size, stride, accounting);
+                let (read, write) = bidir_stream_bytes(&config);
+                assert_eq!(
+                    read + write,
+                    bytes_per_iteration(&config).2,
+                    "{:?} size {} stride {}",
+                    accounting,
+                    size,
+                    stride
+                );
+            }
+        }
+    }
+
+    /// u32 positions one pass of `kernel` touches at offset 0. Writing kernels are run over a
+    /// sentinel and their changes collected; reading kernels are probed one position at a time.
+    fn touched(config: &BenchmarkConfig) -> Vec<usize> {
+        let num_elements = config.size / std::mem::size_of::<u32>();
+        let params = KernelParams {
+            stride: config.stride,
+            prefetch_distance: 0,
+            unroll: 1,
+            tile: config.tile.unwrap_or(1),
+            row_len: config.row_len,
+            block_len: config.block_len.unwrap_or(1),
+            safe: false,
+            offset: 0,
+            stream_isa: detect_stream_isa(),
+        };
+        let buffer = |value: u32| {
+            let mut buffer = Buffer::allocate(num_elements, 64, false);
+            buffer.fill(value);
+            buffer
+        };
+        let changed = |buffer: &[u32], value: u32| -> Vec<usize> {
+            (0..buffer.len()).filter(|&i| buffer[i] != value).collect()
+        };
+
+        let kernel = &config.kernel;
+        match kernel {
+            Kernel::ScalarWrite
+            | Kernel::SimdWrite
+            | Kernel::SimdWriteU64
+            | Kernel::SimdWriteF32 => {
+                let mut data = buffer(u32::MAX);
+                kernel.run(&mut data, &mut [], &params, &[]);
+                changed(&data, u32::MAX)
+            }
+            Kernel::ScalarUpdate | Kernel::SimdUpdate => {
+                let mut data = buffer(0);
+                kernel.run(&mut data, &mut [], &params, &[]);
+                changed(&data, 0)
+            }
+            Kernel::Copy | Kernel::Bidir => {
+                let (mut src, mut dst) = (buffer(1), buffer(u32::MAX));
+                kernel.run(&mut src, &mut dst, &params, &[]);
+                changed(&dst, u32::MAX)
+            }
+            _ => {
+                let one = match kernel {
+                    Kernel::SimdReadF32 => 1.0f32.to_bits(),
+                    _ => 1,
+                };
+                let mut data = buffer(0);
+                (0..num_elements)
+                    .filter(|&i| {
+                        data[i] = one;
+                        let sum = kernel.run(&mut data, &mut [], &params, &[]);
+                        data[i] = 0;
+                        sum != 0
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    #[test]
+    fn accounting_matches_what_each_kernel_touches() {
+        let kernels = [
+            Kernel::ScalarRead,
+            Kernel::ScalarWrite,
+            Kernel::SimdRead,
+            Kernel::SimdWrite,
+            Kernel::StreamRead,
+            Kernel::SimdReadU64,
+            Kernel::SimdWriteU64,
+            Kernel::SimdReadF32,
+            Kernel::SimdWriteF32,
+            Kernel::ScalarUpdate,
+            Kernel::SimdUpdate,
+            Kernel::BlockRead,
+            Kernel::Tiled2D,
+            Kernel::Copy,
+            Kernel::Bidir,
+        ];
+        // Sizes in bytes, including ones that leave a partial vector, line and stride; strides
+        // in u32 elements on both sides of a 64-byte line
+        for kernel in kernels {
+            for size in [4096, 4100, 1000] {
+                for stride in [1, 2, 3, 8, 15, 16, 17, 32, 100] {
+                    let mut config = config(kernel.clone(), size, stride, Accounting::Lines);
+                    config.block_len = Some(stride.min(5));
+                    config.row_len = 24;
+                    config.tile = Some(5);
+
+                    let touched = touched(&config);
+                    let context = format!("{:?} size {} stride {}", kernel, size, stride);
+                    let element_size = kernel.element_size();
+                    let elements = touched.len() * std::mem::size_of::<u32>() / element_size;
+                    let mut lines: Vec<usize> = touched.iter().map(|&i| i * 4 / 64).collect();
+                    lines.dedup();
+
+                    assert_eq!(
+                        line_bytes_per_iteration(&config).2,
+                        lines.len() * 64 * kernel.lines_per_access(),
+                        "lines: {}",
+                        context
+                    );
+                    // Streaming loads read the whole vector around each element, which the
+                    // bytes model deliberately does not count
+                    if kernel != Kernel::StreamRead {
+                        assert_eq!(
+                            referenced_bytes_per_iteration(&config),
+                            elements * element_size * kernel.lines_per_access(),
+                            "bytes: {}",
+                            context
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn accounting_modes_diverge_by_the_unused_share_of_each_line() {
+        // A 16-byte stride references one u32 in four but fetches every line
+        let lines = config(Kernel::ScalarRead, 1 << 20, 4, Accounting::Lines);
+        let bytes = config(Kernel::ScalarRead, 1 << 20, 4, Accounting::Bytes);
+        assert_eq!(bytes_per_iteration(&lines).2, 1 << 20);
+        assert_eq!(bytes_per_iteration(&bytes).2, (1 << 20) / 4);
+
+        // Update moves each line twice under both models
+        let lines = config(Kernel::ScalarUpdate, 1 << 20, 4, Accounting::Lines);
+        let bytes = config(Kernel::ScalarUpdate, 1 << 20, 4, Accounting::Bytes);
+        assert_eq!(bytes_per_iteration(&lines).2, 2 << 20);
+        assert_eq!(bytes_per_iteration(&bytes).2, (2 << 20) / 4);
+
+        // Past a line's width each access fetches a line of its own
+        let lines = config(Kernel::ScalarRead, 1 << 20, 32, Accounting::Lines);
+        let bytes = config(Kernel::ScalarRead, 1 << 20, 32, Accounting::Bytes);
+        assert_eq!(bytes_per_iteration(&lines).2, (1 << 20) / 128 * 64);
+        assert_eq!(bytes_per_iteration(&bytes).2, (1 << 20) / 128 * 4);
+    }
+
+    fn pass_counters(threads: usize) -> PassCounters {
+        PassCounters {
+            setup_done: AtomicBool::new(true),
+            warmup: (0..threads).map(|_| IterationCounter::default()).collect(),
+            timed: Arc::new((0..threads).map(|_| IterationCounter::default()).collect()),
+        }
+    }
+
+    #[test]
+    fn watchdog_catches_a_stuck_warmup() {
+        // Setup is done but no warmup pass ever completes
+        let passes = pass_counters(2);
+        let done = AtomicBool::new(false);
+        let start = Instant::now();
+        assert!(stalled(&passes, &done, Duration::from_millis(50)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn watchdog_follows_warmup_then_timed_passes() {
+        let passes = Arc::new(pass_counters(1));
+        let done = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let passes = Arc::clone(&passes);
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                // Warmup and then timed passes, each well inside the timeout
+                for pass in 1..=10 {
+                    thread::sleep(Duration::from_millis(20));
+                    passes.warmup[0].0.store(pass, Ordering::Relaxed);
+                }
+                for pass in 1..=10 {
+                    thread::sleep(Duration::from_millis(20));
+                    passes.timed[0].0.store(pass, Ordering::Relaxed);
+                }
+                done.store(true, Ordering::Relaxed);
+            })
+        };
+        assert!(!stalled(&passes, &done, Duration::from_millis(150)));
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn watchdog_waits_out_setup() {
+        let passes = pass_counters(1);
+        passes.setup_done.store(false, Ordering::SeqCst);
+        let done = AtomicBool::new(false);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(100));
+                done.store(true, Ordering::Relaxed);
+            });
+            // Setup never finishes within the timeout, which is not a stall
+            assert!(!stalled(&passes, &done, Duration::from_millis(10)));
+        });
+    }
+
+    #[test]
+    fn empty_resident_working_sets_are_rejected_when_parsing() {
+        let parse = |args: &[&str]| Args::try_parse_from(["mallow"].iter().chain(args));
+        assert!(parse(&["--resident", "0"]).is_err());
+        assert_eq!(parse(&["--resident", "32"]).unwrap().resident, Some(32));
+    }
+
+    #[test]
+    fn zero_strides_are_rejected_when_parsing() {
+        let parse = |args: &[&str]| Args::try_parse_from(["mallow"].iter().chain(args));
+        assert!(parse(&["--stride", "0"]).is_err());
+        assert!(parse(&["--stride-sweep", "0:64:2"]).is_err());
+        assert_eq!(parse(&["--stride", "1"]).unwrap().stride, Some(1));
+    }
 }
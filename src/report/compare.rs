@@ -0,0 +1,177 @@
+use super::{format_size, BenchmarkResults, Units};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Bandwidth of one result in the current run next to its matching baseline result
+pub struct Comparison {
+    pub size_mib: f64,
+    pub threads: usize,
+    pub units: Units,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+impl Comparison {
+    /// Percent change from baseline to current; negative means slower
+    pub fn delta_percent(&self) -> f64 {
+        (self.current - self.baseline) / self.baseline * 100.0
+    }
+}
+
+/// Loads results previously written with `--format json`
+pub fn load_baseline(path: &Path) -> io::Result<BenchmarkResults> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(io::Error::other)
+}
+
+/// Pairs each current result with the baseline result that has the same kernel, buffer size,
+/// stride, thread count and units. Results without a match are left out, as are baselines
+/// with no bandwidth, which no change can be measured against.
+pub fn compare(baseline: &BenchmarkResults, current: &BenchmarkResults) -> Vec<Comparison> {
+    if baseline.config.kernel != current.config.kernel {
+        return Vec::new();
+    }
+
+    current
+        .results
+        .iter()
+        .filter_map(|result| {
+            baseline
+                .results
+                .iter()
+                .find(|base| {
+                    base.size_mib == result.size_mib
+                        && base.threads == result.threads
+                        && base.units == result.units
+                        // Baselines recorded before strides were tracked match any stride
+                        && (base.stride_bytes == 0 || base.stride_bytes == result.stride_bytes)
+                        && base.cpu_node == result.cpu_node
+                        && base.mem_node == result.mem_node
+                        && base.ccd_placement == result.ccd_placement
+                        // Baselines recorded before kernel names match on the config's kernel
+                        && (base.kernel_name.is_empty() || base.kernel_name == result.kernel_name)
+                        && base.bandwidth_gib_s > 0.0
+                })
+                .map(|base| Comparison {
+                    size_mib: result.size_mib,
+                    threads: result.threads,
+                    units: result.units,
+                    baseline: base.bandwidth_gib_s,
+                    current: result.bandwidth_gib_s,
+                })
+        })
+        .collect()
+}
+
+/// Writes the comparison table. It is written even with `--quiet`, since a CI job reading the
+/// exit code of `--fail-under` needs to see which result regressed.
+pub fn print_comparison(comparisons: &[Comparison], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "\nBaseline Comparison")?;
+    writeln!(out, "================================")?;
+    if comparisons.is_empty() {
+        writeln!(
+            out,
+            "No results match the baseline (kernel, size, thread count and units must agree)"
+        )?;
+        return Ok(());
+    }
+    let units = comparisons[0].units.label();
+    writeln!(
+        out,
+        "Buffer Size\tThreads\tBaseline ({})\tCurrent ({})\tDelta",
+        units, units
+    )?;
+    writeln!(
+        out,
+        "------------------------------------------------------------------------"
+    )?;
+    for comparison in comparisons {
+        writeln!(
+            out,
+            "{}\t{}\t{:.2}\t\t\t{:.2}\t\t{:+.1}%",
+            format_size(comparison.size_mib),
+            comparison.threads,
+            comparison.baseline,
+            comparison.current,
+            comparison.delta_percent()
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::Kernel;
+    use crate::report::tests::{result, results_of};
+    use serde_json::json;
+
+    fn bandwidths(comparisons: &[Comparison]) -> Vec<(f64, f64)> {
+        comparisons
+            .iter()
+            .map(|c| (c.baseline, c.current))
+            .collect()
+    }
+
+    #[test]
+    fn pairs_results_that_agree_on_every_dimension() {
+        let baseline = results_of(vec![
+            result(json!({"bandwidth_gib_s": 10.0})),
+            result(json!({"bandwidth_gib_s": 20.0, "threads": 2})),
+        ]);
+        let current = results_of(vec![
+            result(json!({"bandwidth_gib_s": 19.0, "threads": 2})),
+            result(json!({"bandwidth_gib_s": 11.0})),
+        ]);
+
+        let comparisons = compare(&baseline, &current);
+        assert_eq!(bandwidths(&comparisons), vec![(20.0, 19.0), (10.0, 11.0)]);
+        assert!((comparisons[0].delta_percent() - -5.0).abs() < 1e-9);
+        assert!((comparisons[1].delta_percent() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn leaves_out_results_that_differ_on_any_dimension() {
+        let baseline = results_of(vec![result(json!({"cpu_node": 0, "mem_node": 0}))]);
+        for differing in [
+            json!({"size_mib": 64.0}),
+            json!({"threads": 4}),
+            json!({"units": "gb"}),
+            json!({"stride_bytes": 128}),
+            json!({"kernel_name": "SimdRead"}),
+            json!({"cpu_node": 0, "mem_node": 1}),
+            json!({"cpu_node": 0, "mem_node": 0, "ccd_placement": "spread"}),
+        ] {
+            let mut extra = differing.clone();
+            let object = extra.as_object_mut().unwrap();
+            object.entry("cpu_node").or_insert(json!(0));
+            object.entry("mem_node").or_insert(json!(0));
+            let current = results_of(vec![result(extra)]);
+            assert!(compare(&baseline, &current).is_empty(), "{}", differing);
+        }
+
+        // Results of a different configured kernel never match
+        let mut current = results_of(vec![result(json!({"cpu_node": 0, "mem_node": 0}))]);
+        assert_eq!(compare(&baseline, &current).len(), 1);
+        current.config.kernel = Kernel::SimdRead;
+        assert!(compare(&baseline, &current).is_empty());
+    }
+
+    #[test]
+    fn legacy_baselines_match_any_stride_and_kernel_name() {
+        let baseline = results_of(vec![result(json!({"stride_bytes": 0, "kernel_name": ""}))]);
+        let current = results_of(vec![result(json!({
+            "stride_bytes": 256,
+            "kernel_name": "ScalarReadPrefetch",
+        }))]);
+        assert_eq!(compare(&baseline, &current).len(), 1);
+    }
+
+    #[test]
+    fn zero_bandwidth_baselines_are_not_compared() {
+        let baseline = results_of(vec![result(json!({"bandwidth_gib_s": 0.0}))]);
+        let current = results_of(vec![result(json!({}))]);
+        assert!(compare(&baseline, &current).is_empty());
+    }
+}
@@ -1,80 +1,700 @@
+pub mod compare;
+pub mod stats;
+mod yaml;
+
+use crate::buffer::{Fill, MemType};
 use crate::kernel::Kernel;
+use crate::report::stats::{AutoDuration, SteadyCriterion, TimingHistogram};
 use crate::system::cpu_info::CacheInfo;
+use crate::system::host::{hostname, MachineInfo};
 use core_affinity::CoreId;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// How the bytes a kernel pass moves are counted
+///
+/// `Lines` models the memory traffic: every access fetches a whole cache line, so sub-line
+/// strides count each line once and strides past a line count a line per element. `Bytes`
+/// counts only the element bytes the kernel references, which is how STREAM-style kernels
+/// report bandwidth. The two agree for unit-stride streaming kernels, where every byte of every
+/// line is referenced; use `bytes` to compare against STREAM figures and `lines` to see what
+/// strided access costs the memory system.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Accounting {
+    #[default]
+    Lines,
+    Bytes,
+}
+
+/// How --ccd-placement lays threads out over the L3 domains (CCXs) of a chiplet CPU
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CcdPlacement {
+    /// Every thread on the cores of a single L3 domain
+    Packed,
+    /// Threads dealt round-robin across the L3 domains
+    Spread,
+}
+
+impl CcdPlacement {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CcdPlacement::Packed => "one CCD",
+            CcdPlacement::Spread => "across CCDs",
+        }
+    }
+}
+
+/// Unit bandwidth figures are reported in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    /// Gibibytes per second (2^30 bytes)
+    #[default]
+    Gib,
+    /// Gigabytes per second (10^9 bytes), as used by DRAM spec sheets and STREAM
+    Gb,
+}
+
+impl Units {
+    /// Bytes in one unit
+    pub fn bytes(&self) -> f64 {
+        match self {
+            Units::Gib => 1024.0 * 1024.0 * 1024.0,
+            Units::Gb => 1e9,
+        }
+    }
 
-#[derive(Clone, Serialize)]
+    /// Unit of an amount of data, e.g. "GiB"
+    pub fn amount(&self) -> &'static str {
+        match self {
+            Units::Gib => "GiB",
+            Units::Gb => "GB",
+        }
+    }
+
+    /// Unit of a rate, e.g. "GiB/s"
+    pub fn label(&self) -> &'static str {
+        match self {
+            Units::Gib => "GiB/s",
+            Units::Gb => "GB/s",
+        }
+    }
+
+    /// Suffix for machine-readable column and metric names, e.g. "gib_s"
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Units::Gib => "gib_s",
+            Units::Gb => "gb_s",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BenchmarkConfig {
     pub size: usize,
     pub stride: usize,
+    pub prefetch_distance: usize,
+    pub unroll: usize,
+    /// Tile edge length and row length in elements, only used by the tiled 2D kernel
+    #[serde(default)]
+    pub tile: Option<usize>,
+    #[serde(default)]
+    pub row_len: usize,
+    /// Contiguous elements read at each strided position, only used by the blocked kernel
+    #[serde(default)]
+    pub block_len: Option<usize>,
+    /// Populated memory channels, from --channels
+    #[serde(default)]
+    pub channels: Option<usize>,
+    /// Independent chains walked by the multi-chain chase kernel
+    #[serde(default)]
+    pub chains: Option<usize>,
+    /// Whether the SIMD kernels used bounds-checked scatters
+    #[serde(default)]
+    pub safe: bool,
+    /// Length of the bandwidth sampling windows, if sampling was enabled
+    #[serde(default)]
+    pub sample_interval_ms: Option<u64>,
+    pub alignment: usize,
+    pub hugepages: bool,
+    pub prefault: bool,
+    #[serde(default)]
+    pub fill: Fill,
+    /// Memory type of the buffers, and the device file write-combining memory is mapped from
+    #[serde(default)]
+    pub memtype: MemType,
+    #[serde(default)]
+    pub wc_path: Option<PathBuf>,
+    /// File the source buffers are mapped from instead of anonymous memory, under --mmap-file
+    #[serde(default)]
+    pub mmap_file: Option<PathBuf>,
     pub duration_secs: f64,
+    /// Fixed number of timed passes per thread, replacing `duration_secs` when set
+    #[serde(default)]
+    pub iterations: Option<usize>,
+    /// Fewest timed passes, summed over threads, a duration-based run may end with. Runs that
+    /// fall short at `duration_secs` keep going until they reach it.
+    #[serde(default)]
+    pub min_iterations: Option<usize>,
     pub warmup_iterations: usize,
+    pub warmup_secs: Option<f64>,
+    /// Kernel the warmup runs when it differs from `kernel`, under --warmup-kernel
+    #[serde(default)]
+    pub warmup_kernel: Option<Kernel>,
     pub kernel: Kernel,
     pub thread_count: usize,
     #[serde(skip)]
     pub core_ids: Vec<CoreId>,
     pub cpu_cache_info: CacheInfo,
+    #[serde(default)]
+    pub units: Units,
+    #[serde(default)]
+    pub accounting: Accounting,
+    /// Steady-state criterion under --steady-state, which reports the mean of the sampling
+    /// windows after steady state instead of the whole timed region
+    #[serde(default)]
+    pub steady_state: Option<SteadyCriterion>,
+    /// Under --auto-duration, runs last from `duration_secs` up to `max_secs`, until the
+    /// window bandwidths are precise enough
+    #[serde(default)]
+    pub auto_duration: Option<AutoDuration>,
+    /// Whether each thread recorded the duration of every timed pass
+    #[serde(default)]
+    pub record_timings: bool,
+    /// Whether hardware performance counters were requested
+    #[serde(default)]
+    pub perf: bool,
+    /// SCHED_FIFO priority requested for the worker threads, under --rt-priority
+    #[serde(default)]
+    pub rt_priority: Option<u8>,
+    /// NUMA node whose CPUs run the threads and node the buffers are bound to, under
+    /// --numa-matrix
+    #[serde(default)]
+    pub cpu_node: Option<usize>,
+    #[serde(default)]
+    pub mem_node: Option<usize>,
+    /// Thread layout over the L3 domains, under --ccd-placement
+    #[serde(default)]
+    pub ccd_placement: Option<CcdPlacement>,
 }
 
-#[derive(Serialize)]
+impl BenchmarkConfig {
+    /// Longest a duration-based run lasts: `duration_secs`, or the --auto-duration ceiling
+    pub fn run_limit_secs(&self) -> f64 {
+        self.auto_duration
+            .map_or(self.duration_secs, |auto| auto.max_secs)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct BenchmarkResult {
     pub size_mib: f64,
+    /// Stride between accesses in bytes, as requested (0 in results that predate it)
+    #[serde(default)]
+    pub stride_bytes: usize,
+    /// Measured bandwidth in `units` (the name predates `--units`)
     pub bandwidth_gib_s: f64,
+    #[serde(default)]
+    pub units: Units,
     pub simd_enabled: bool,
     pub parallel_enabled: bool,
     pub affinity_enabled: bool,
+    pub hugepages_enabled: bool,
     pub iterations: usize,
     pub warmup_iterations: usize,
     pub threads: usize,
+    pub level: Option<String>,
+    /// Bandwidth of each thread in `units`, over its own iterations and elapsed time
+    pub per_thread: Vec<f64>,
+    /// Measured length of the timed region, shorter than requested if interrupted
+    pub elapsed_secs: f64,
+    pub interrupted: bool,
+    /// False if the run was interrupted, strayed from the requested duration by more than
+    /// --tolerance, or completed too few iterations to trust
+    #[serde(default = "assume_reliable")]
+    pub reliable: bool,
+    /// When the measurement started, as an RFC3339 UTC timestamp
+    #[serde(default)]
+    pub timestamp: String,
+    /// Theoretical peak DRAM bandwidth in `units`, from --peak-gbps or detected DIMM speeds
+    pub peak_gib_s: Option<f64>,
+    /// Achieved bandwidth as a percentage of `peak_gib_s`
+    pub efficiency_percent: Option<f64>,
+    /// Last-level cache misses per kernel iteration, when --perf counters were available
+    #[serde(default)]
+    pub cache_misses_per_iteration: Option<f64>,
+    /// Memory channels the bandwidth is split across, from --channels
+    #[serde(default)]
+    pub channels: Option<usize>,
+    /// Aggregate bandwidth divided by `channels`, in `units`
+    #[serde(default)]
+    pub bandwidth_per_channel: Option<f64>,
+    /// Average time per dependent load of one chain, for the multi-chain chase kernel
+    #[serde(default)]
+    pub latency_ns: Option<f64>,
+    /// Operations per byte of the kernel, the x axis of a roofline plot against `bandwidth_gib_s`
+    #[serde(default)]
+    pub ops_per_byte: f64,
+    /// Aggregate bandwidth of each sampling window in `units`, when --sample-interval was set
+    #[serde(default)]
+    pub bandwidth_windows: Vec<f64>,
+    /// Whether the last sampling window fell below the first by more than the threshold
+    #[serde(default)]
+    pub throttled: bool,
+    /// Instructions per cycle over the timed region, when --perf counters were available
+    #[serde(default)]
+    pub ipc: Option<f64>,
+    /// False if --affinity was requested and any thread could not be pinned to its core
+    #[serde(default = "assume_reliable")]
+    pub affinity_ok: bool,
+    /// Whether every worker thread ran under SCHED_FIFO, false unless --rt-priority was given
+    #[serde(default)]
+    pub realtime: bool,
+    /// Assigned and observed placement of each thread
+    #[serde(default)]
+    pub thread_placement: Vec<ThreadPlacement>,
+    /// Kernel that produced this result, e.g. "SimdRead" (empty in results that predate it)
+    #[serde(default)]
+    pub kernel_name: String,
+    /// Relative standard error of the sampling window bandwidths in percent, under
+    /// --auto-duration; `elapsed_secs` is the duration the run extended to
+    #[serde(default)]
+    pub rse_percent: Option<f64>,
+    /// Distribution of individual pass durations across all threads, under --record-timings
+    #[serde(default)]
+    pub pass_timings: Option<TimingHistogram>,
+    /// Share of `bandwidth_gib_s` that was reads and writes, for kernels with separate read and
+    /// write streams
+    #[serde(default)]
+    pub read_bandwidth: Option<f64>,
+    #[serde(default)]
+    pub write_bandwidth: Option<f64>,
+    /// Bounds of the 95% confidence interval for `bandwidth_gib_s` under --steady-state, over
+    /// the sampling windows after the first `discarded_windows`
+    #[serde(default)]
+    pub ci95_low: Option<f64>,
+    #[serde(default)]
+    pub ci95_high: Option<f64>,
+    #[serde(default)]
+    pub discarded_windows: Option<usize>,
+    /// Which of the byte counts below `bandwidth_gib_s` is based on
+    #[serde(default)]
+    pub accounting: Accounting,
+    /// Bytes one kernel pass moves counted as whole cache lines, and counted as the element
+    /// bytes it references (0 in results that predate them)
+    #[serde(default)]
+    pub line_bytes_per_iteration: usize,
+    #[serde(default)]
+    pub referenced_bytes_per_iteration: usize,
+    /// NUMA nodes the threads ran on and the buffers were bound to, under --numa-matrix
+    #[serde(default)]
+    pub cpu_node: Option<usize>,
+    #[serde(default)]
+    pub mem_node: Option<usize>,
+    /// Thread layout over the L3 domains, under --ccd-placement
+    #[serde(default)]
+    pub ccd_placement: Option<CcdPlacement>,
 }
 
-#[derive(Serialize)]
+/// Where one worker thread was asked to run and where it found itself
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ThreadPlacement {
+    /// Core the thread was pinned to, `None` without --affinity
+    pub assigned_core: Option<usize>,
+    /// Whether the pinning call succeeded
+    pub pinned: bool,
+    /// CPU the thread was running on once pinned, where the OS reports it
+    pub observed_cpu: Option<usize>,
+}
+
+impl ThreadPlacement {
+    /// False if the thread was assigned a core but is not provably on it
+    pub fn honored(&self) -> bool {
+        match self.assigned_core {
+            Some(core) => self.pinned && self.observed_cpu.is_none_or(|cpu| cpu == core),
+            None => true,
+        }
+    }
+}
+
+/// Version of the serialized results layout, bumped whenever a change would break an existing
+/// parser (a field removed, renamed or retyped). New optional fields do not bump it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Everything one invocation produced, as written by the JSON, YAML and baseline formats.
+///
+/// Schema version 1:
+/// - `schema_version`: this layout's version, 0 when read from output that predates it
+/// - `results`: one `BenchmarkResult` per measured size and stride, in run order. Bandwidth
+///   figures (`bandwidth_gib_s`, `per_thread`, `peak_gib_s`, `bandwidth_windows`) are in the
+///   result's `units` despite the field names
+/// - `config`: the `BenchmarkConfig` shared by all runs; `size` and `stride` are those of the
+///   first run, sweeps record theirs per result
+/// - `machine`: the `MachineInfo` of the host that ran the benchmark
+/// - `summary`: headline figures across all results, see `Summary`
+/// - `groups`: only with --group-results, the same results nested by each dimension the run
+///   varied, outermost first: `by_size` (keyed by cache level, or buffer size in bytes),
+///   `by_stride` (bytes), `by_numa` (`cpu<N>_mem<M>`), `by_placement` (`packed` or `spread`)
+///   and `by_kernel`. Size is used when nothing varied. Keys are ordered as strings; the flat
+///   `results` array keeps run order.
+///
+/// The ndjson format emits each result on its own line with `schema_version`, `kernel`,
+/// `duration_secs` and `iterations_per_thread` added.
+#[derive(Serialize, Deserialize)]
 pub struct BenchmarkResults {
+    #[serde(default)]
+    pub schema_version: u32,
     pub results: Vec<BenchmarkResult>,
     pub config: BenchmarkConfig,
+    /// Only emitted in JSON; missing from results saved before it was added
+    #[serde(default)]
+    pub machine: MachineInfo,
+    #[serde(default)]
+    pub summary: Summary,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groups: Option<serde_json::Value>,
+}
+
+/// Key of a result along each dimension --group-results can nest by, in run order
+type GroupKey = fn(&BenchmarkResult) -> String;
+
+const GROUP_DIMENSIONS: [(&str, GroupKey); 5] = [
+    ("by_size", |r| match &r.level {
+        Some(level) => level.clone(),
+        None => ((r.size_mib * 1024.0 * 1024.0).round() as u64).to_string(),
+    }),
+    ("by_stride", |r| r.stride_bytes.to_string()),
+    ("by_numa", |r| match (r.cpu_node, r.mem_node) {
+        (Some(cpu), Some(mem)) => format!("cpu{}_mem{}", cpu, mem),
+        _ => "none".to_string(),
+    }),
+    ("by_placement", |r| match r.ccd_placement {
+        Some(CcdPlacement::Packed) => "packed".to_string(),
+        Some(CcdPlacement::Spread) => "spread".to_string(),
+        None => "none".to_string(),
+    }),
+    ("by_kernel", |r| r.kernel_name.clone()),
+];
+
+/// Nests `results` by every dimension that differs between them, for the `groups` field. A
+/// leaf holds the result itself, or an array if several share every key.
+pub fn group_results(results: &[BenchmarkResult]) -> serde_json::Value {
+    let mut dimensions: Vec<(&str, GroupKey)> = GROUP_DIMENSIONS
+        .into_iter()
+        .filter(|(_, key)| {
+            results
+                .windows(2)
+                .any(|pair| key(&pair[0]) != key(&pair[1]))
+        })
+        .collect();
+    if dimensions.is_empty() {
+        dimensions.push(GROUP_DIMENSIONS[0]);
+    }
+    let results: Vec<&BenchmarkResult> = results.iter().collect();
+    nest(&results, &dimensions)
 }
 
-pub fn print_results(results: &BenchmarkResults, format: &str) {
+fn nest(results: &[&BenchmarkResult], dimensions: &[(&str, GroupKey)]) -> serde_json::Value {
+    let Some(((name, key), rest)) = dimensions.split_first() else {
+        return match results {
+            [result] => serde_json::to_value(result).unwrap(),
+            _ => serde_json::to_value(results).unwrap(),
+        };
+    };
+    let mut groups = serde_json::Map::new();
+    for result in results {
+        let value = key(result);
+        if groups.contains_key(&value) {
+            continue;
+        }
+        let members: Vec<&BenchmarkResult> = results
+            .iter()
+            .copied()
+            .filter(|r| key(r) == value)
+            .collect();
+        groups.insert(value, nest(&members, rest));
+    }
+    let mut outer = serde_json::Map::new();
+    outer.insert(name.to_string(), serde_json::Value::Object(groups));
+    serde_json::Value::Object(outer)
+}
+
+/// Single-number figures across every result of a run
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Summary {
+    /// Geometric mean of the results' bandwidths in `units`, the appropriate average for
+    /// rates. Equal to the bandwidth itself for a single result.
+    pub geomean_gib_s: f64,
+}
+
+impl Summary {
+    /// Summarizes `results`, skipping any with no measured bandwidth (e.g. interrupted before
+    /// the first pass) since they would zero the geometric mean
+    pub fn of(results: &[BenchmarkResult]) -> Summary {
+        let logs: Vec<f64> = results
+            .iter()
+            .filter(|r| r.bandwidth_gib_s > 0.0)
+            .map(|r| r.bandwidth_gib_s.ln())
+            .collect();
+        let geomean_gib_s = if logs.is_empty() {
+            0.0
+        } else {
+            (logs.iter().sum::<f64>() / logs.len() as f64).exp()
+        };
+        Summary { geomean_gib_s }
+    }
+}
+
+/// Results saved before reliability was tracked are taken at face value
+fn assume_reliable() -> bool {
+    true
+}
+
+/// Value of a result in one table column, unquoted
+type Cell = fn(&BenchmarkResult) -> String;
+
+/// Columns of the CSV and markdown tables, with each one's markdown alignment. Both formats
+/// are generated from this list so they always carry the same fields. The bandwidth column's
+/// name gets the units suffix.
+const TABLE_COLUMNS: [(&str, &str, Cell); 11] = [
+    ("size_mib", "---:", |r| format!("{:.1}", r.size_mib)),
+    ("bandwidth", "---:", |r| format!("{:.2}", r.bandwidth_gib_s)),
+    ("simd", ":---:", |r| r.simd_enabled.to_string()),
+    ("parallel", ":---:", |r| r.parallel_enabled.to_string()),
+    ("affinity", ":---:", |r| r.affinity_enabled.to_string()),
+    ("threads", "---:", |r| r.threads.to_string()),
+    ("iterations", "---:", |r| r.iterations.to_string()),
+    ("stride_bytes", "---:", |r| r.stride_bytes.to_string()),
+    ("elapsed_secs", "---:", |r| format!("{:.3}", r.elapsed_secs)),
+    ("timestamp", ":---", |r| r.timestamp.clone()),
+    ("kernel", ":---", |r| r.kernel_name.clone()),
+];
+
+fn column_names(units: Units) -> impl Iterator<Item = String> {
+    TABLE_COLUMNS.iter().map(move |(name, _, _)| match *name {
+        "bandwidth" => format!("bandwidth_{}", units.suffix()),
+        name => name.to_string(),
+    })
+}
+
+/// Column names matching the rows produced by `csv_row`
+fn csv_header(units: Units) -> String {
+    column_names(units).collect::<Vec<_>>().join(",")
+}
+
+/// Quotes a CSV field per RFC 4180 when it contains a separator, quote or line break, doubling
+/// any quotes inside it. Other fields are written as is.
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+fn csv_row(result: &BenchmarkResult) -> String {
+    TABLE_COLUMNS
+        .iter()
+        .map(|(_, _, cell)| csv_field(&cell(result)).into_owned())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Writes the table columns as a markdown table, escaping pipes inside cells
+fn print_markdown(results: &BenchmarkResults, out: &mut dyn Write) -> io::Result<()> {
+    let row = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+    writeln!(out, "{}", row(column_names(results.config.units).collect()))?;
+    writeln!(
+        out,
+        "|{}|",
+        TABLE_COLUMNS.map(|(_, align, _)| align).join("|")
+    )?;
+    for result in &results.results {
+        let cells = TABLE_COLUMNS
+            .iter()
+            .map(|(_, _, cell)| cell(result).replace('|', "\\|"))
+            .collect();
+        writeln!(out, "{}", row(cells))?;
+    }
+    Ok(())
+}
+
+/// Appends one CSV row per result to `path`, prefixed with the hostname so rows from different
+/// machines can share a file (each row already carries its own timestamp). The header is only
+/// written when the file is new or empty, and all rows go out in a single append write so
+/// concurrent runs cannot interleave partial lines.
+pub fn append_csv(results: &BenchmarkResults, path: &Path, header: bool) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let header = header && file.metadata()?.len() == 0;
+    file.write_all(appended_rows(results, &hostname(), header).as_bytes())
+}
+
+/// The lines `append_csv` writes for `results` on `hostname`
+fn appended_rows(results: &BenchmarkResults, hostname: &str, header: bool) -> String {
+    let mut content = String::new();
+    if header {
+        content.push_str(&format!("hostname,{}\n", csv_header(results.config.units)));
+    }
+    let hostname = csv_field(hostname);
+    for result in &results.results {
+        content.push_str(&format!("{},{}\n", hostname, csv_row(result)));
+    }
+    content
+}
+
+/// One result as a compact single-line JSON object, with the kernel and run length from the
+/// config added so each line stands alone
+pub fn ndjson_line(result: &BenchmarkResult, config: &BenchmarkConfig) -> String {
+    let mut value = serde_json::to_value(result).unwrap();
+    if let serde_json::Value::Object(map) = &mut value {
+        map.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+        map.insert(
+            "kernel".to_string(),
+            serde_json::to_value(&config.kernel).unwrap(),
+        );
+        map.insert("duration_secs".to_string(), config.duration_secs.into());
+        map.insert(
+            "iterations_per_thread".to_string(),
+            serde_json::to_value(config.iterations).unwrap(),
+        );
+    }
+    value.to_string()
+}
+
+/// Values `print_results` understands for `format`; anything else falls back to text
+pub const FORMATS: [&str; 8] = [
+    "text",
+    "csv",
+    "json",
+    "ndjson",
+    "yaml",
+    "prometheus",
+    "markdown",
+    "plot",
+];
+
+/// Writes `results` in `format`. `header` controls whether CSV output starts with its header
+/// line.
+pub fn print_results(
+    results: &BenchmarkResults,
+    format: &str,
+    header: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let units = results.config.units;
     match format {
         "json" => {
-            println!("{}", serde_json::to_string_pretty(&results).unwrap());
+            writeln!(out, "{}", serde_json::to_string_pretty(&results).unwrap())?;
         }
+        "ndjson" => {
+            for result in &results.results {
+                writeln!(out, "{}", ndjson_line(result, &results.config))?;
+            }
+        }
+        "yaml" => {
+            write!(out, "{}", yaml::to_string(&results).unwrap())?;
+        }
+        "prometheus" => print_prometheus(results, out)?,
+        "plot" => print_plot(results, out)?,
+        "markdown" => print_markdown(results, out)?,
         "csv" => {
-            println!("size_mib,bandwidth_gib_s,simd,parallel,affinity,threads,iterations");
+            if header {
+                writeln!(out, "{}", csv_header(results.config.units))?;
+            }
             for result in &results.results {
-                println!(
-                    "{:.1},{:.2},{},{},{},{},{}",
-                    result.size_mib,
-                    result.bandwidth_gib_s,
-                    result.simd_enabled,
-                    result.parallel_enabled,
-                    result.affinity_enabled,
-                    result.threads,
-                    result.iterations
-                );
+                writeln!(out, "{}", csv_row(result))?;
             }
         }
         _ => {
-            println!(
+            writeln!(
+                out,
                 "\nMemory {} Bandwidth Benchmark",
                 match results.config.kernel {
-                    Kernel::ScalarRead | Kernel::SimdRead => "Read",
-                    Kernel::ScalarWrite | Kernel::SimdWrite => "Write",
-                }
-            );
-            println!("================================");
-            println!(
-                "Running for {:.1} seconds ({} warmup iterations)",
-                results.config.duration_secs, results.config.warmup_iterations
-            );
+                    Kernel::ScalarRead
+                    | Kernel::SimdRead
+                    | Kernel::SimdReadU64
+                    | Kernel::SimdReadF32
+                    | Kernel::StreamRead
+                    | Kernel::MultiChase
+                    | Kernel::RandomRead
+                    | Kernel::Tiled2D
+                    | Kernel::BlockRead => "Read",
+                    Kernel::ScalarWrite
+                    | Kernel::SimdWrite
+                    | Kernel::SimdWriteU64
+                    | Kernel::SimdWriteF32 => "Write",
+                    Kernel::ScalarUpdate | Kernel::SimdUpdate => "Update",
+                    Kernel::Copy => "Copy",
+                    Kernel::Bidir => "Bidirectional",
+                }
+            )?;
+            writeln!(out, "================================")?;
+            let length = match results.config.iterations {
+                Some(iterations) => format!("Running {} iterations per thread", iterations),
+                None => match results.config.auto_duration {
+                    Some(auto) => format!(
+                        "Running for {:.1} to {:.1} seconds, until the relative standard error is below {:.2}%",
+                        results.config.duration_secs, auto.max_secs, auto.target_rse_percent
+                    ),
+                    None => match results.config.min_iterations {
+                        Some(floor) => format!(
+                            "Running for {:.1} seconds and at least {} iterations",
+                            results.config.duration_secs, floor
+                        ),
+                        None => format!("Running for {:.1} seconds", results.config.duration_secs),
+                    },
+                },
+            };
+            match results.config.warmup_secs {
+                Some(warmup_secs) => {
+                    writeln!(out, "{} ({:.1} seconds warmup)", length, warmup_secs)?
+                }
+                None => writeln!(
+                    out,
+                    "{} ({} warmup iterations)",
+                    length, results.config.warmup_iterations
+                )?,
+            }
+            if let Some(warmup_kernel) = &results.config.warmup_kernel {
+                writeln!(out, "Warming up with the {:?} kernel", warmup_kernel)?;
+            }
+            if results.config.accounting == Accounting::Bytes {
+                writeln!(
+                    out,
+                    "Counting referenced bytes rather than whole cache lines"
+                )?;
+            }
+            for result in results.results.iter().filter(|r| r.interrupted) {
+                match results.config.iterations {
+                    Some(iterations) => writeln!(
+                        out,
+                        "Interrupted after {} of {} iterations ({:.1} seconds)",
+                        result.iterations / result.threads,
+                        iterations,
+                        result.elapsed_secs
+                    )?,
+                    None => writeln!(
+                        out,
+                        "Interrupted after {:.1} of {:.1} seconds",
+                        result.elapsed_secs, results.config.duration_secs
+                    )?,
+                }
+            }
             let is_parallel = results.config.thread_count > 1;
             if is_parallel {
-                println!(
+                writeln!(
+                    out,
                     "Parallel execution with {} threads",
                     results.config.thread_count
-                );
+                )?;
                 if !results.config.core_ids.is_empty() {
-                    println!(
+                    writeln!(
+                        out,
                         "Core affinity enabled: {:?}",
                         results
                             .config
@@ -82,29 +702,935 @@ pub fn print_results(results: &BenchmarkResults, format: &str) {
                             .iter()
                             .map(|id| id.id)
                             .collect::<Vec<_>>()
-                    );
+                    )?;
+                }
+            }
+            if let Some(priority) = results.config.rt_priority {
+                if results.results.iter().all(|r| r.realtime) {
+                    writeln!(
+                        out,
+                        "Real-time scheduling: SCHED_FIFO priority {}",
+                        priority
+                    )?;
+                } else {
+                    writeln!(
+                        out,
+                        "Real-time scheduling requested (priority {}) but not obtained",
+                        priority
+                    )?;
                 }
             }
             match results.config.kernel {
-                Kernel::SimdRead | Kernel::SimdWrite => println!("SIMD enabled (8-wide u32)"),
-                Kernel::ScalarRead | Kernel::ScalarWrite => println!("Scalar operations"),
+                Kernel::SimdRead | Kernel::SimdWrite | Kernel::SimdUpdate => {
+                    writeln!(out, "SIMD enabled (8-wide u32)")?
+                }
+                Kernel::SimdReadU64 | Kernel::SimdWriteU64 => {
+                    writeln!(out, "SIMD enabled (8-wide u64)")?
+                }
+                Kernel::SimdReadF32 | Kernel::SimdWriteF32 => {
+                    writeln!(out, "SIMD enabled (8-wide f32)")?
+                }
+                Kernel::ScalarRead
+                | Kernel::ScalarWrite
+                | Kernel::ScalarUpdate
+                | Kernel::Copy
+                | Kernel::Bidir => writeln!(out, "Scalar operations")?,
+                Kernel::MultiChase => writeln!(
+                    out,
+                    "Dependent loads over {} interleaved random chains",
+                    results.config.chains.unwrap_or_default()
+                )?,
+                Kernel::StreamRead => writeln!(out, "Non-temporal streaming loads (MOVNTDQA)")?,
+                Kernel::Tiled2D => writeln!(
+                    out,
+                    "Tiled 2D walk ({} x {} element tiles, rows of {} elements)",
+                    results.config.tile.unwrap_or_default(),
+                    results.config.tile.unwrap_or_default(),
+                    results.config.row_len
+                )?,
+                Kernel::BlockRead => writeln!(
+                    out,
+                    "Blocked strided reads ({} contiguous elements per stride)",
+                    results.config.block_len.unwrap_or_default()
+                )?,
+                Kernel::RandomRead => {
+                    writeln!(out, "Random access (uniformly random precomputed indices)")?
+                }
             }
-            println!("\nBuffer Size\tBandwidth (GiB/s)\tFlags\t\tThreads\tIterations");
-            println!("------------------------------------------------------------------------");
+            if results.config.prefetch_distance > 0 {
+                writeln!(
+                    out,
+                    "Software prefetch distance: {} accesses",
+                    results.config.prefetch_distance
+                )?;
+            }
+            let peak = results.results.iter().find_map(|r| r.peak_gib_s);
+            let has_perf = results
+                .results
+                .iter()
+                .any(|r| r.ipc.is_some() || r.cache_misses_per_iteration.is_some());
+            let by_stride = stride_varies(results);
+            if let Some(peak) = peak {
+                writeln!(out, "Theoretical peak: {:.2} {}", peak, units.label())?;
+            }
+            writeln!(
+                out,
+                "\nBuffer Size\t{}Bandwidth ({})\tFlags\t\tThreads\tIterations{}{}",
+                if by_stride { "Stride\t" } else { "" },
+                units.label(),
+                if peak.is_some() { "\tEfficiency" } else { "" },
+                if has_perf { "\tMisses/iter\tIPC" } else { "" }
+            )?;
+            writeln!(
+                out,
+                "------------------------------------------------------------------------"
+            )?;
             for result in &results.results {
-                let flags = format!(
+                let mut flags = format!(
                     "SIMD={}, PAR={}, AFF={}",
                     result.simd_enabled, result.parallel_enabled, result.affinity_enabled
                 );
-                println!(
-                    "{:.1} MiB\t{:.2} GiB/s\t{}\t{}\t{}",
-                    result.size_mib,
+                if results.config.hugepages {
+                    flags.push_str(&format!(", HUGE={}", result.hugepages_enabled));
+                }
+                if !result.reliable {
+                    flags.push_str(", UNRELIABLE");
+                }
+                let efficiency = result
+                    .efficiency_percent
+                    .map(|e| format!("\t{:.1}%", e))
+                    .unwrap_or_default();
+                // Either counter may be missing on its own when the PMU lacks it
+                let counters = if has_perf {
+                    format!(
+                        "\t{}\t{}",
+                        result
+                            .cache_misses_per_iteration
+                            .map_or("-".to_string(), |misses| format!("{:.1}", misses)),
+                        result
+                            .ipc
+                            .map_or("-".to_string(), |ipc| format!("{:.2}", ipc))
+                    )
+                } else {
+                    String::new()
+                };
+                let stride = if by_stride {
+                    format!("{} B\t", result.stride_bytes)
+                } else {
+                    String::new()
+                };
+                writeln!(
+                    out,
+                    "{}\t{}{:.2} {}\t{}\t{}\t{}{}{}",
+                    size_label(result),
+                    stride,
                     result.bandwidth_gib_s,
+                    units.label(),
                     flags,
                     result.threads,
-                    result.iterations
-                );
+                    result.iterations,
+                    efficiency,
+                    counters
+                )?;
+            }
+            if results.results.len() > 1 {
+                writeln!(
+                    out,
+                    "Geometric mean: {:.2} {}",
+                    results.summary.geomean_gib_s,
+                    units.label()
+                )?;
+            }
+            for result in &results.results {
+                if let (Some(channels), Some(per_channel)) =
+                    (result.channels, result.bandwidth_per_channel)
+                {
+                    let of_peak = result
+                        .peak_gib_s
+                        .map(|peak| {
+                            let peak_per_channel = peak / channels as f64;
+                            format!(
+                                " of {:.2} {} peak ({:.1}%)",
+                                peak_per_channel,
+                                units.label(),
+                                per_channel / peak_per_channel * 100.0
+                            )
+                        })
+                        .unwrap_or_default();
+                    writeln!(
+                        out,
+                        "{}: {:.2} {} per channel across {} channels{}",
+                        size_label(result),
+                        per_channel,
+                        units.label(),
+                        channels,
+                        of_peak
+                    )?;
+                }
+                if let (Some(low), Some(high), Some(discarded)) =
+                    (result.ci95_low, result.ci95_high, result.discarded_windows)
+                {
+                    writeln!(
+                        out,
+                        "{}: {:.2} {} \u{b1} {:.2} (95% CI {:.2} to {:.2}), {} of {} windows discarded before steady state",
+                        size_label(result),
+                        result.bandwidth_gib_s,
+                        units.label(),
+                        (high - low) / 2.0,
+                        low,
+                        high,
+                        discarded,
+                        result.bandwidth_windows.len()
+                    )?;
+                }
+                if let Some(rse) = result.rse_percent {
+                    writeln!(
+                        out,
+                        "{}: ran {:.1} s to a relative standard error of {:.2}%",
+                        size_label(result),
+                        result.elapsed_secs,
+                        rse
+                    )?;
+                }
+                if let (Some(read), Some(write)) = (result.read_bandwidth, result.write_bandwidth) {
+                    writeln!(
+                        out,
+                        "{}: {:.2} {} read + {:.2} {} write",
+                        size_label(result),
+                        read,
+                        units.label(),
+                        write,
+                        units.label()
+                    )?;
+                }
+                if let Some(latency) = result.latency_ns {
+                    writeln!(
+                        out,
+                        "{}: {:.1} ns per dependent access",
+                        size_label(result),
+                        latency
+                    )?;
+                }
+                if let [first, .., last] = result.bandwidth_windows[..] {
+                    writeln!(
+                        out,
+                        "{}: first window {:.2} {}, last window {:.2} {} ({:+.1}%){}",
+                        size_label(result),
+                        first,
+                        units.label(),
+                        last,
+                        units.label(),
+                        (last - first) / first * 100.0,
+                        if result.throttled {
+                            ", throttling detected"
+                        } else {
+                            ""
+                        }
+                    )?;
+                }
+            }
+            print_numa_matrix(results, out)?;
+            print_ccd_comparison(results, out)?;
+            print_kernel_comparison(results, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Scalar kernel a SIMD kernel is compared against under --compare
+fn scalar_counterpart(kernel_name: &str) -> Option<&'static str> {
+    match kernel_name {
+        "SimdRead" => Some("ScalarRead"),
+        "SimdWrite" => Some("ScalarWrite"),
+        "SimdUpdate" => Some("ScalarUpdate"),
+        _ => None,
+    }
+}
+
+/// Lists results from more than one kernel side by side, with each SIMD kernel's speedup over
+/// its scalar counterpart at the same size and stride. Does nothing for single-kernel runs.
+fn print_kernel_comparison(results: &BenchmarkResults, out: &mut dyn Write) -> io::Result<()> {
+    let Some(first) = results.results.first() else {
+        return Ok(());
+    };
+    if results
+        .results
+        .iter()
+        .all(|r| r.kernel_name == first.kernel_name)
+    {
+        return Ok(());
+    }
+
+    let units = results.config.units;
+    writeln!(out, "\nKernel Comparison")?;
+    writeln!(
+        out,
+        "Buffer Size\tKernel\t\tBandwidth ({})\tSpeedup",
+        units.label()
+    )?;
+    writeln!(
+        out,
+        "------------------------------------------------------------"
+    )?;
+    for result in &results.results {
+        let speedup = scalar_counterpart(&result.kernel_name)
+            .and_then(|scalar| {
+                results.results.iter().find(|r| {
+                    r.kernel_name == scalar
+                        && r.size_mib == result.size_mib
+                        && r.stride_bytes == result.stride_bytes
+                        && r.cpu_node == result.cpu_node
+                        && r.mem_node == result.mem_node
+                        && r.ccd_placement == result.ccd_placement
+                })
+            })
+            .filter(|scalar| scalar.bandwidth_gib_s > 0.0)
+            .map(|scalar| {
+                format!(
+                    "{:.2}x vs {}",
+                    result.bandwidth_gib_s / scalar.bandwidth_gib_s,
+                    scalar.kernel_name
+                )
+            })
+            .unwrap_or_default();
+        writeln!(
+            out,
+            "{}\t{:<12}\t{:.2} {}\t{}",
+            size_label(result),
+            result.kernel_name,
+            result.bandwidth_gib_s,
+            units.label(),
+            speedup
+        )?;
+    }
+    Ok(())
+}
+
+/// Puts the packed and spread bandwidth of each --ccd-placement pair side by side, with how
+/// much spreading across CCDs gains. Does nothing for results without CCD placement.
+fn print_ccd_comparison(results: &BenchmarkResults, out: &mut dyn Write) -> io::Result<()> {
+    let packed: Vec<&BenchmarkResult> = results
+        .results
+        .iter()
+        .filter(|r| r.ccd_placement == Some(CcdPlacement::Packed))
+        .collect();
+    if packed.is_empty() {
+        return Ok(());
+    }
+
+    let units = results.config.units;
+    writeln!(out, "\nCCD Placement ({})", units.label())?;
+    writeln!(out, "Buffer Size\tOne CCD\t\tAcross CCDs\tSpread/Packed")?;
+    writeln!(
+        out,
+        "------------------------------------------------------------"
+    )?;
+    for result in packed {
+        let spread = results.results.iter().find(|r| {
+            r.ccd_placement == Some(CcdPlacement::Spread)
+                && r.size_mib == result.size_mib
+                && r.stride_bytes == result.stride_bytes
+                && r.kernel_name == result.kernel_name
+        });
+        let (spread, ratio) = match spread {
+            Some(spread) if result.bandwidth_gib_s > 0.0 => (
+                format!("{:.2}", spread.bandwidth_gib_s),
+                format!("{:.2}x", spread.bandwidth_gib_s / result.bandwidth_gib_s),
+            ),
+            _ => ("-".to_string(), "-".to_string()),
+        };
+        let size = match &result.level {
+            Some(level) => format!("{} {}", level, format_size(result.size_mib)),
+            None => format_size(result.size_mib),
+        };
+        writeln!(
+            out,
+            "{}\t{:.2}\t\t{}\t\t{}",
+            size, result.bandwidth_gib_s, spread, ratio
+        )?;
+    }
+    Ok(())
+}
+
+/// Renders --numa-matrix results as a grid with a row per CPU node and a column per memory
+/// node. Does nothing for results without NUMA placement.
+fn print_numa_matrix(results: &BenchmarkResults, out: &mut dyn Write) -> io::Result<()> {
+    let cells: Vec<(usize, usize, f64)> = results
+        .results
+        .iter()
+        .filter_map(|r| Some((r.cpu_node?, r.mem_node?, r.bandwidth_gib_s)))
+        .collect();
+    if cells.is_empty() {
+        return Ok(());
+    }
+    let mut nodes: Vec<usize> = cells.iter().flat_map(|&(c, m, _)| [c, m]).collect();
+    nodes.sort_unstable();
+    nodes.dedup();
+
+    writeln!(
+        out,
+        "\nNUMA bandwidth matrix ({}, rows: CPU node, columns: memory node)",
+        results.config.units.label()
+    )?;
+    let header: Vec<String> = nodes.iter().map(|n| format!("mem {}", n)).collect();
+    writeln!(out, "\t{}", header.join("\t"))?;
+    for &cpu in &nodes {
+        let row: Vec<String> = nodes
+            .iter()
+            .map(|&mem| {
+                cells
+                    .iter()
+                    .find(|&&(c, m, _)| c == cpu && m == mem)
+                    .map_or("-".to_string(), |&(_, _, bandwidth)| {
+                        format!("{:.2}", bandwidth)
+                    })
+            })
+            .collect();
+        writeln!(out, "cpu {}\t{}", cpu, row.join("\t"))?;
+    }
+    Ok(())
+}
+
+/// Prints the runs a benchmark would execute, one config per run with its cache level, as
+/// JSON for the structured formats and as a table otherwise
+pub fn print_plan(
+    runs: &[(Option<String>, BenchmarkConfig)],
+    format: &str,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    match format {
+        "json" | "ndjson" | "yaml" => {
+            let plan: Vec<serde_json::Value> = runs
+                .iter()
+                .map(|(level, config)| {
+                    let mut value = serde_json::to_value(config).unwrap();
+                    if let serde_json::Value::Object(map) = &mut value {
+                        map.insert("level".to_string(), serde_json::to_value(level).unwrap());
+                        // core_ids is skipped in results but is part of what the plan decides
+                        map.insert(
+                            "core_ids".to_string(),
+                            config.core_ids.iter().map(|id| id.id).collect(),
+                        );
+                    }
+                    value
+                })
+                .collect();
+            match format {
+                "yaml" => write!(out, "{}", yaml::to_string(&plan).unwrap())?,
+                _ => writeln!(out, "{}", serde_json::to_string_pretty(&plan).unwrap())?,
+            }
+        }
+        _ => {
+            let Some((_, first)) = runs.first() else {
+                return writeln!(out, "Nothing to run");
+            };
+            writeln!(out, "\nBenchmark Plan ({} runs)", runs.len())?;
+            writeln!(out, "================================")?;
+            writeln!(out, "Kernel:    {:?}", first.kernel)?;
+            writeln!(out, "Threads:   {}", first.thread_count)?;
+            if first.core_ids.is_empty() {
+                writeln!(out, "Affinity:  none")?;
+            } else {
+                writeln!(
+                    out,
+                    "Affinity:  {:?}",
+                    first.core_ids.iter().map(|id| id.id).collect::<Vec<_>>()
+                )?;
+            }
+            let cache = &first.cpu_cache_info;
+            let levels = [
+                ("L1D", cache.l1d_size_kb),
+                ("L2", cache.l2_size_kb),
+                ("L3", cache.l3_size_kb),
+            ];
+            let detected: Vec<String> = levels
+                .iter()
+                .filter_map(|(name, kb)| kb.map(|kb| format!("{} {} KB", name, kb)))
+                .collect();
+            if detected.is_empty() {
+                writeln!(out, "Caches:    not detected")?;
+            } else {
+                writeln!(out, "Caches:    {}", detected.join(", "))?;
+            }
+            match first.iterations {
+                Some(iterations) => {
+                    writeln!(out, "Length:    {} iterations per thread", iterations)?
+                }
+                None => writeln!(out, "Length:    {:.1} seconds per run", first.duration_secs)?,
+            }
+            writeln!(out)?;
+            writeln!(out, "Run\tBuffer Size\tStride")?;
+            writeln!(out, "--------------------------------")?;
+            for (i, (level, config)) in runs.iter().enumerate() {
+                let size = format_size(config.size as f64 / (1024.0 * 1024.0));
+                let size = match level {
+                    Some(level) => format!("{} {}", level, size),
+                    None => size,
+                };
+                let size = match (config.cpu_node, config.mem_node) {
+                    (Some(cpu), Some(mem)) => format!("{} (cpu {}, mem {})", size, cpu, mem),
+                    _ => size,
+                };
+                let size = match config.ccd_placement {
+                    Some(placement) => format!("{} ({})", size, placement.label()),
+                    None => size,
+                };
+                writeln!(
+                    out,
+                    "{}\t{}\t{} B",
+                    i + 1,
+                    size,
+                    config.stride * std::mem::size_of::<u32>()
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Formats a buffer size given in MiB, switching to KiB for sub-MiB buffers
+pub fn format_size(size_mib: f64) -> String {
+    if size_mib < 1.0 {
+        format!("{:.1} KiB", size_mib * 1024.0)
+    } else {
+        format!("{:.1} MiB", size_mib)
+    }
+}
+
+/// Whether results were taken at more than one stride, as with `--stride-sweep`
+fn stride_varies(results: &BenchmarkResults) -> bool {
+    results
+        .results
+        .windows(2)
+        .any(|pair| pair[0].stride_bytes != pair[1].stride_bytes)
+}
+
+/// Buffer size for display, prefixed with the cache level when the result has one and
+/// followed by its NUMA placement under --numa-matrix or CCD placement under --ccd-placement
+fn size_label(result: &BenchmarkResult) -> String {
+    let size = match &result.level {
+        Some(level) => format!("{} {}", level, format_size(result.size_mib)),
+        None => format_size(result.size_mib),
+    };
+    let size = match (result.cpu_node, result.mem_node) {
+        (Some(cpu), Some(mem)) => format!("{} (cpu {}, mem {})", size, cpu, mem),
+        _ => size,
+    };
+    match result.ccd_placement {
+        Some(placement) => format!("{} ({})", size, placement.label()),
+        None => size,
+    }
+}
+
+/// Emits a minimal two-column dataset for gnuplot. The x axis is the thread count or the
+/// stride when that is the only thing varying across results (thread scaling, `--stride-sweep`),
+/// and the buffer size in bytes otherwise (size sweeps such as `--levels`).
+fn print_plot(results: &BenchmarkResults, out: &mut dyn Write) -> io::Result<()> {
+    let first = results.results.first();
+    let same_size = results
+        .results
+        .iter()
+        .all(|r| Some(r.size_mib) == first.map(|f| f.size_mib));
+    let by_threads = results
+        .results
+        .iter()
+        .any(|r| Some(r.threads) != first.map(|f| f.threads))
+        && same_size;
+
+    if same_size && stride_varies(results) {
+        writeln!(
+            out,
+            "# stride_bytes bandwidth_{}",
+            results.config.units.suffix()
+        )?;
+        for result in &results.results {
+            writeln!(out, "{} {}", result.stride_bytes, result.bandwidth_gib_s)?;
+        }
+    } else if by_threads {
+        writeln!(out, "# threads bandwidth_{}", results.config.units.suffix())?;
+        for result in &results.results {
+            writeln!(out, "{} {}", result.threads, result.bandwidth_gib_s)?;
+        }
+    } else {
+        writeln!(
+            out,
+            "# size_bytes bandwidth_{}",
+            results.config.units.suffix()
+        )?;
+        for result in &results.results {
+            let size_bytes = (result.size_mib * 1024.0 * 1024.0) as usize;
+            writeln!(out, "{} {}", size_bytes, result.bandwidth_gib_s)?;
+        }
+    }
+    Ok(())
+}
+
+/// Escapes a label value for the Prometheus text exposition format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Emits results in the Prometheus text exposition format for node_exporter's textfile collector
+fn print_prometheus(results: &BenchmarkResults, out: &mut dyn Write) -> io::Result<()> {
+    let labels: Vec<String> = results
+        .results
+        .iter()
+        .map(|result| {
+            // Results before kernel names were recorded all ran the config's kernel
+            let kernel = match result.kernel_name.as_str() {
+                "" => format!("{:?}", results.config.kernel),
+                name => name.to_string(),
+            };
+            let mut pairs = vec![
+                ("kernel", kernel),
+                ("simd", result.simd_enabled.to_string()),
+                ("threads", result.threads.to_string()),
+                ("size_mib", result.size_mib.to_string()),
+                ("stride_bytes", result.stride_bytes.to_string()),
+            ];
+            if let Some(level) = &result.level {
+                pairs.push(("level", level.clone()));
+            }
+            // --numa-matrix results differ only in their nodes
+            if let (Some(cpu), Some(mem)) = (result.cpu_node, result.mem_node) {
+                pairs.push(("cpu_node", cpu.to_string()));
+                pairs.push(("mem_node", mem.to_string()));
+            }
+            // Likewise for the two halves of --ccd-placement
+            if let Some(placement) = result.ccd_placement {
+                let name = match placement {
+                    CcdPlacement::Packed => "packed",
+                    CcdPlacement::Spread => "spread",
+                };
+                pairs.push(("placement", name.to_string()));
+            }
+            pairs
+                .iter()
+                .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect();
+
+    let units = results.config.units;
+    let metric = format!(
+        "mallow_bandwidth_{}_per_second",
+        units.amount().to_lowercase()
+    );
+    writeln!(
+        out,
+        "# HELP {} Measured memory bandwidth in {}",
+        metric,
+        units.label()
+    )?;
+    writeln!(out, "# TYPE {} gauge", metric)?;
+    for (result, labels) in results.results.iter().zip(&labels) {
+        writeln!(out, "{}{{{}}} {}", metric, labels, result.bandwidth_gib_s)?;
+    }
+
+    writeln!(
+        out,
+        "# HELP mallow_iterations Number of kernel passes completed in the timed region"
+    )?;
+    writeln!(out, "# TYPE mallow_iterations gauge")?;
+    for (result, labels) in results.results.iter().zip(&labels) {
+        writeln!(out, "mallow_iterations{{{}}} {}", labels, result.iterations)?;
+    }
+
+    // Only the chase kernel measures latency, so the family is left out when nothing has one
+    if results.results.iter().any(|r| r.latency_ns.is_some()) {
+        writeln!(
+            out,
+            "# HELP mallow_latency_ns Average time per dependent load of one chain in nanoseconds"
+        )?;
+        writeln!(out, "# TYPE mallow_latency_ns gauge")?;
+        for (result, labels) in results.results.iter().zip(&labels) {
+            if let Some(latency) = result.latency_ns {
+                writeln!(out, "mallow_latency_ns{{{}}} {}", labels, latency)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A result with only the fields every result carries, plus `extra` on top
+    pub(super) fn result(extra: serde_json::Value) -> serde_json::Value {
+        let mut result = json!({
+            "size_mib": 128.0,
+            "stride_bytes": 64,
+            "bandwidth_gib_s": 12.5,
+            "simd_enabled": false,
+            "parallel_enabled": false,
+            "affinity_enabled": false,
+            "hugepages_enabled": false,
+            "iterations": 20,
+            "warmup_iterations": 5,
+            "threads": 1,
+            "per_thread": [12.5],
+            "elapsed_secs": 0.25,
+            "interrupted": false,
+            "timestamp": "2026-10-15T04:44:04Z",
+            "kernel_name": "ScalarRead",
+        });
+        result
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        result
+    }
+
+    pub(super) fn results_of(results: Vec<serde_json::Value>) -> BenchmarkResults {
+        serde_json::from_value(json!({
+            "results": results,
+            "config": {
+                "size": 128 << 20,
+                "stride": 16,
+                "prefetch_distance": 0,
+                "unroll": 1,
+                "alignment": 64,
+                "hugepages": false,
+                "prefault": true,
+                "duration_secs": 0.25,
+                "warmup_iterations": 5,
+                "kernel": "ScalarRead",
+                "thread_count": 1,
+                "cpu_cache_info": {},
+            },
+        }))
+        .unwrap()
+    }
+
+    fn render(results: &BenchmarkResults, format: &str) -> String {
+        let mut out = Vec::new();
+        print_results(results, format, true, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Splits a markdown table row into its trimmed cells
+    fn markdown_cells(line: &str) -> Vec<String> {
+        let inner = line
+            .trim()
+            .strip_prefix('|')
+            .unwrap()
+            .strip_suffix('|')
+            .unwrap();
+        inner
+            .split(" | ")
+            .map(|cell| cell.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn markdown_columns_match_csv_columns() {
+        let results = results_of(vec![
+            result(json!({})),
+            result(json!({"stride_bytes": 128})),
+        ]);
+        let csv = render(&results, "csv");
+        let markdown = render(&results, "markdown");
+        let csv: Vec<&str> = csv.lines().collect();
+        let markdown: Vec<&str> = markdown.lines().collect();
+
+        // Header, alignment row and one row per result
+        assert_eq!(markdown.len(), csv.len() + 1);
+        assert_eq!(markdown_cells(markdown[0]).join(","), csv[0]);
+        assert_eq!(markdown_cells(markdown[2]).join(","), csv[1]);
+        assert_eq!(markdown_cells(markdown[3]).join(","), csv[2]);
+        assert!(csv[0].contains("stride_bytes") && csv[0].contains("elapsed_secs"));
+    }
+
+    #[test]
+    fn compared_kernels_get_their_own_series_and_column() {
+        let results = results_of(vec![
+            result(json!({"kernel_name": "ScalarRead"})),
+            result(json!({"kernel_name": "SimdRead", "simd_enabled": true})),
+            result(json!({"kernel_name": "MultiChase", "latency_ns": 85.5})),
+        ]);
+
+        let prometheus = render(&results, "prometheus");
+        let series: Vec<&str> = prometheus
+            .lines()
+            .filter(|line| line.starts_with("mallow_bandwidth"))
+            .collect();
+        assert!(series[0].contains("kernel=\"ScalarRead\""), "{}", series[0]);
+        assert!(series[1].contains("kernel=\"SimdRead\""), "{}", series[1]);
+
+        // Latency gets its own family, with a sample only for the result that measured it
+        assert!(prometheus.contains("# TYPE mallow_latency_ns gauge\n"));
+        assert!(prometheus.contains("# HELP mallow_latency_ns "));
+        let latency: Vec<&str> = prometheus
+            .lines()
+            .filter(|line| line.starts_with("mallow_latency_ns"))
+            .collect();
+        assert_eq!(latency.len(), 1, "{}", prometheus);
+        assert!(
+            latency[0].contains("kernel=\"MultiChase\""),
+            "{}",
+            latency[0]
+        );
+        assert!(latency[0].ends_with("} 85.5"), "{}", latency[0]);
+        let without_latency = results_of(vec![result(json!({}))]);
+        assert!(!render(&without_latency, "prometheus").contains("mallow_latency_ns"));
+
+        let csv = render(&results, "csv");
+        let csv: Vec<&str> = csv.lines().collect();
+        assert!(csv[0].ends_with(",kernel"));
+        assert!(csv[1].ends_with(",ScalarRead") && csv[2].ends_with(",SimdRead"));
+
+        let markdown = render(&results, "markdown");
+        let rows: Vec<Vec<String>> = markdown.lines().map(markdown_cells).collect();
+        assert_eq!(rows[0].last().unwrap(), "kernel");
+        assert_eq!(rows[3].last().unwrap(), "SimdRead");
+    }
+
+    #[test]
+    fn numa_matrix_cells_get_their_own_series() {
+        let results = results_of(vec![
+            result(json!({"cpu_node": 0, "mem_node": 0})),
+            result(json!({"cpu_node": 0, "mem_node": 1})),
+        ]);
+
+        let prometheus = render(&results, "prometheus");
+        let series: Vec<&str> = prometheus
+            .lines()
+            .filter(|line| line.starts_with("mallow_bandwidth"))
+            .collect();
+        assert!(
+            series[0].contains("cpu_node=\"0\",mem_node=\"0\""),
+            "{}",
+            series[0]
+        );
+        assert!(
+            series[1].contains("cpu_node=\"0\",mem_node=\"1\""),
+            "{}",
+            series[1]
+        );
+
+        let plain = results_of(vec![result(json!({}))]);
+        assert!(!render(&plain, "prometheus").contains("cpu_node"));
+    }
+
+    #[test]
+    fn ccd_placements_get_their_own_series() {
+        let results = results_of(vec![
+            result(json!({"ccd_placement": "packed"})),
+            result(json!({"ccd_placement": "spread"})),
+        ]);
+
+        let prometheus = render(&results, "prometheus");
+        let series: Vec<&str> = prometheus
+            .lines()
+            .filter(|line| line.starts_with("mallow_bandwidth"))
+            .collect();
+        assert!(series[0].contains("placement=\"packed\""), "{}", series[0]);
+        assert!(series[1].contains("placement=\"spread\""), "{}", series[1]);
+    }
+
+    #[test]
+    fn markdown_carries_each_results_timestamp() {
+        let results = results_of(vec![
+            result(json!({"timestamp": "2026-10-15T04:44:04Z"})),
+            result(json!({"timestamp": "2026-10-15T04:44:05Z"})),
+        ]);
+        let markdown = render(&results, "markdown");
+        let rows: Vec<Vec<String>> = markdown.lines().map(markdown_cells).collect();
+
+        let column = rows[0].iter().position(|name| name == "timestamp").unwrap();
+        assert_eq!(rows[2][column], "2026-10-15T04:44:04Z");
+        assert_eq!(rows[3][column], "2026-10-15T04:44:05Z");
+    }
+
+    #[test]
+    fn json_written_to_a_file_reads_back_unchanged() {
+        let results = results_of(vec![
+            result(json!({})),
+            result(json!({"size_mib": 0.5, "threads": 2, "per_thread": [6.0, 6.5]})),
+        ]);
+        let path = std::env::temp_dir().join(format!("mallow-output-{}.json", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        print_results(&results, "json", true, &mut file).unwrap();
+        drop(file);
+
+        let loaded = compare::load_baseline(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(loaded.unwrap()).unwrap(),
+            serde_json::to_value(&results).unwrap()
+        );
+    }
+
+    /// Splits RFC 4180 CSV into records of unquoted fields
+    fn parse_csv(content: &str) -> Vec<Vec<String>> {
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = content.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, quoted) {
+                ('"', true) if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                ('"', _) => quoted = !quoted,
+                (',', false) => record.push(std::mem::take(&mut field)),
+                ('\n', false) => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                _ => field.push(c),
             }
         }
+        records
+    }
+
+    #[test]
+    fn csv_fields_with_separators_and_quotes_round_trip() {
+        for value in ["plain", "a,b", "say \"hi\"", "\"", "two\nlines", ",\",\n"] {
+            let quoted = csv_field(value);
+            assert_eq!(parse_csv(&format!("{}\n", quoted)), vec![vec![value]]);
+        }
+        assert!(matches!(csv_field("12.500"), Cow::Borrowed("12.500")));
+    }
+
+    #[test]
+    fn appended_csv_rows_quote_awkward_hostnames_and_cells() {
+        let results = results_of(vec![
+            result(json!({})),
+            result(json!({"timestamp": "15 Oct, \"late\"", "kernel_name": "Block,Read"})),
+        ]);
+        let hostname = "rack \"7\", node 3";
+        let content = appended_rows(&results, hostname, true);
+        let records = parse_csv(&content);
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].join(","),
+            format!("hostname,{}", csv_header(Units::Gib))
+        );
+        for record in &records {
+            assert_eq!(record.len(), records[0].len());
+        }
+        assert_eq!(records[1][0], hostname);
+        assert_eq!(records[2][0], hostname);
+        assert_eq!(records[2].last().unwrap(), "Block,Read");
+        let column = records[0]
+            .iter()
+            .position(|name| name == "timestamp")
+            .unwrap();
+        assert_eq!(records[2][column], "15 Oct, \"late\"");
+
+        // Numeric columns are never quoted, so their text is unchanged
+        let plain = appended_rows(&results, "host", false);
+        assert!(plain.starts_with(&format!("host,{}\n", csv_row(&results.results[0]))));
+        assert!(!csv_row(&results.results[0]).contains('"'));
     }
 }
@@ -0,0 +1,338 @@
+//! Steady-state detection, confidence intervals and run-length control over bandwidth
+//! sampling windows.
+
+use serde::{Deserialize, Serialize};
+
+/// When a run counts as having reached steady state: the coefficient of variation of
+/// `windows` consecutive sampling windows falls below `cv_percent`
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SteadyCriterion {
+    pub windows: usize,
+    pub cv_percent: f64,
+}
+
+/// When --auto-duration ends a run: once the relative standard error of the window bandwidths
+/// falls below `target_rse_percent` after the minimum duration, or at `max_secs` regardless
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AutoDuration {
+    pub target_rse_percent: f64,
+    pub max_secs: f64,
+}
+
+/// Bandwidth over the steady-state windows of a run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SteadyState {
+    /// Windows before steady state was reached, excluded from the figures below
+    pub discarded: usize,
+    /// Windows the figures are computed over
+    pub windows: usize,
+    pub mean: f64,
+    /// Bounds of the 95% confidence interval for the mean, from the t-distribution
+    pub ci95_low: f64,
+    pub ci95_high: f64,
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Sample standard deviation (n - 1 denominator). Needs at least two values.
+fn std_dev(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    let sum_sq: f64 = values.iter().map(|v| (v - mean) * (v - mean)).sum();
+    (sum_sq / (values.len() - 1) as f64).sqrt()
+}
+
+/// Standard error of the mean as a percentage of the mean. `None` for fewer than two values
+/// or a zero mean.
+pub fn relative_standard_error(values: &[f64]) -> Option<f64> {
+    let mean = mean(values);
+    (values.len() >= 2 && mean > 0.0)
+        .then(|| std_dev(values) / (values.len() as f64).sqrt() / mean * 100.0)
+}
+
+/// Two-sided 95% critical value of Student's t-distribution with `df` degrees of freedom.
+/// Exact to three decimals up to 30, then stepped down towards the normal 1.960.
+fn t_critical_95(df: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match df {
+        0 => f64::INFINITY,
+        1..=30 => TABLE[df - 1],
+        31..=40 => 2.021,
+        41..=60 => 2.000,
+        61..=120 => 1.980,
+        _ => 1.960,
+    }
+}
+
+/// Finds the first run of `criterion.windows` consecutive windows whose coefficient of
+/// variation is below the threshold, discards the windows before it, and computes the mean
+/// and its 95% confidence interval over everything from there on. Returns `None` if steady
+/// state is never reached.
+pub fn steady_state(windows: &[f64], criterion: &SteadyCriterion) -> Option<SteadyState> {
+    let k = criterion.windows.max(2);
+    let start = windows.windows(k).position(|group| {
+        let mean = mean(group);
+        mean > 0.0 && std_dev(group) / mean * 100.0 < criterion.cv_percent
+    })?;
+
+    let steady = &windows[start..];
+    let mean = mean(steady);
+    let half_width =
+        t_critical_95(steady.len() - 1) * std_dev(steady) / (steady.len() as f64).sqrt();
+    Some(SteadyState {
+        discarded: start,
+        windows: steady.len(),
+        mean,
+        ci95_low: mean - half_width,
+        ci95_high: mean + half_width,
+    })
+}
+
+/// Sub-buckets per power of two in `PassTimings`, giving buckets about 19% wide
+const SUB_BUCKETS: u32 = 4;
+
+/// Per-pass durations counted into log-spaced nanosecond buckets, cheap enough to update
+/// inside the timed loop and to merge across threads
+#[derive(Clone, Debug)]
+pub struct PassTimings {
+    counts: Vec<u64>,
+}
+
+impl Default for PassTimings {
+    fn default() -> Self {
+        PassTimings {
+            counts: vec![0; (u64::BITS * SUB_BUCKETS) as usize],
+        }
+    }
+}
+
+/// One non-empty bucket of a `TimingHistogram`, covering `[lower_ns, upper_ns)`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TimingBucket {
+    pub lower_ns: u64,
+    pub upper_ns: u64,
+    pub count: u64,
+}
+
+/// Distribution of kernel pass durations over the timed region, across all threads
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TimingHistogram {
+    pub buckets: Vec<TimingBucket>,
+}
+
+impl PassTimings {
+    /// Bucket holding `ns`: exact below `SUB_BUCKETS`, then `SUB_BUCKETS` equal slices of
+    /// each power of two
+    fn bucket(ns: u64) -> usize {
+        let shift = SUB_BUCKETS.trailing_zeros();
+        if ns < SUB_BUCKETS as u64 {
+            return ns as usize;
+        }
+        let octave = u64::BITS - 1 - ns.leading_zeros();
+        let sub = (ns >> (octave - shift)) as u32 & (SUB_BUCKETS - 1);
+        ((octave - shift + 1) * SUB_BUCKETS + sub) as usize
+    }
+
+    /// Lower bound of bucket `index`, the inverse of `bucket`. Wider than `u64` because the
+    /// bucket holding `u64::MAX` ends at 2^64.
+    fn lower_bound(index: usize) -> u128 {
+        let index = index as u32;
+        if index < SUB_BUCKETS {
+            return index as u128;
+        }
+        let scale = index / SUB_BUCKETS - 1;
+        ((SUB_BUCKETS + index % SUB_BUCKETS) as u128) << scale
+    }
+
+    pub fn record(&mut self, ns: u64) {
+        self.counts[Self::bucket(ns)] += 1;
+    }
+
+    pub fn merge(&mut self, other: &PassTimings) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+    }
+
+    pub fn histogram(&self) -> TimingHistogram {
+        let buckets = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(i, &count)| TimingBucket {
+                lower_ns: Self::lower_bound(i) as u64,
+                // Only the last bucket's end overflows, and it saturates
+                upper_ns: u64::try_from(Self::lower_bound(i + 1)).unwrap_or(u64::MAX),
+                count,
+            })
+            .collect();
+        TimingHistogram { buckets }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} differs from {}",
+            actual,
+            expected
+        );
+    }
+
+    fn criterion(windows: usize, cv_percent: f64) -> SteadyCriterion {
+        SteadyCriterion {
+            windows,
+            cv_percent,
+        }
+    }
+
+    #[test]
+    fn discards_the_warm_up_ramp() {
+        // [10.0, 10.2, 9.8] is the first triple with a CV under 5%
+        let windows = [5.0, 8.0, 10.0, 10.2, 9.8, 10.0, 10.0];
+        let steady = steady_state(&windows, &criterion(3, 5.0)).unwrap();
+        assert_eq!(steady.discarded, 2);
+        assert_eq!(steady.windows, 5);
+        assert_close(steady.mean, 10.0);
+
+        // Sample standard deviation sqrt(0.08 / 4), and t = 2.776 for 4 degrees of freedom
+        let half_width = 2.776 * (0.08f64 / 4.0).sqrt() / 5f64.sqrt();
+        assert_close(steady.ci95_low, 10.0 - half_width);
+        assert_close(steady.ci95_high, 10.0 + half_width);
+    }
+
+    #[test]
+    fn flat_runs_keep_every_window() {
+        let windows = [4.0, 4.0, 4.0, 4.0];
+        let steady = steady_state(&windows, &criterion(3, 1.0)).unwrap();
+        assert_eq!(steady.discarded, 0);
+        assert_eq!(steady.windows, 4);
+        assert_close(steady.ci95_low, 4.0);
+        assert_close(steady.ci95_high, 4.0);
+    }
+
+    #[test]
+    fn runs_that_never_settle_have_no_steady_state() {
+        let ramp = [1.0, 2.0, 4.0, 8.0, 16.0, 32.0];
+        assert_eq!(steady_state(&ramp, &criterion(3, 5.0)), None);
+        // Too few windows to form a single group
+        assert_eq!(steady_state(&[10.0, 10.0], &criterion(3, 5.0)), None);
+        // A zero mean never counts as steady
+        assert_eq!(steady_state(&[0.0; 5], &criterion(3, 5.0)), None);
+    }
+
+    #[test]
+    fn groups_are_at_least_two_windows() {
+        // A single window has no spread, so 0 and 1 behave like 2: the first steady pair is
+        // [10.0, 10.1]
+        let windows = [1.0, 10.0, 10.1];
+        for windows_per_group in [0, 1, 2] {
+            let steady = steady_state(&windows, &criterion(windows_per_group, 5.0)).unwrap();
+            assert_eq!(steady.discarded, 1, "{} windows", windows_per_group);
+            assert_eq!(steady.windows, 2);
+            // 1 degree of freedom: t = 12.706
+            let half_width = 12.706 * (0.005f64).sqrt() / 2f64.sqrt();
+            assert_close(steady.ci95_high - steady.mean, half_width);
+        }
+    }
+
+    #[test]
+    fn t_values_step_towards_the_normal_distribution() {
+        assert_eq!(t_critical_95(0), f64::INFINITY);
+        assert_eq!(t_critical_95(1), 12.706);
+        assert_eq!(t_critical_95(4), 2.776);
+        assert_eq!(t_critical_95(30), 2.042);
+        assert_eq!(t_critical_95(31), 2.021);
+        assert_eq!(t_critical_95(60), 2.000);
+        assert_eq!(t_critical_95(120), 1.980);
+        assert_eq!(t_critical_95(121), 1.960);
+        for df in 1..200 {
+            assert!(t_critical_95(df + 1) <= t_critical_95(df), "df {}", df);
+        }
+    }
+
+    #[test]
+    fn relative_standard_error_is_a_percentage_of_the_mean() {
+        // Mean 10, sample standard deviation 1, standard error 1 / sqrt(3)
+        let rse = relative_standard_error(&[9.0, 10.0, 11.0]).unwrap();
+        assert_close(rse, 100.0 / 3f64.sqrt() / 10.0);
+        assert_eq!(relative_standard_error(&[10.0]), None);
+        assert_eq!(relative_standard_error(&[0.0, 0.0]), None);
+    }
+
+    #[test]
+    fn every_duration_falls_inside_its_bucket() {
+        let powers = (0..64).flat_map(|shift| {
+            let power = 1u64 << shift;
+            [power - 1, power, power.saturating_add(1)]
+        });
+        for ns in (0..=4096).chain(powers).chain([u64::MAX - 1, u64::MAX]) {
+            let bucket = PassTimings::bucket(ns);
+            assert!(bucket < PassTimings::default().counts.len(), "{} ns", ns);
+            assert!(PassTimings::lower_bound(bucket) <= ns as u128, "{} ns", ns);
+            assert!(
+                (ns as u128) < PassTimings::lower_bound(bucket + 1),
+                "{} ns",
+                ns
+            );
+        }
+    }
+
+    #[test]
+    fn buckets_are_exact_below_the_sub_bucket_count() {
+        for ns in 0..SUB_BUCKETS as u64 {
+            assert_eq!(PassTimings::bucket(ns), ns as usize);
+            assert_eq!(PassTimings::lower_bound(ns as usize), ns as u128);
+        }
+        // From there on each power of two splits into SUB_BUCKETS equal slices
+        assert_eq!(PassTimings::lower_bound(PassTimings::bucket(1000)), 896);
+        assert_eq!(
+            PassTimings::lower_bound(PassTimings::bucket(1000) + 1),
+            1024
+        );
+    }
+
+    #[test]
+    fn merging_sums_counts_across_threads() {
+        let mut first = PassTimings::default();
+        let mut second = PassTimings::default();
+        for ns in [100, 100, 5000] {
+            first.record(ns);
+        }
+        for ns in [100, 1_000_000, u64::MAX] {
+            second.record(ns);
+        }
+        first.merge(&second);
+
+        let histogram = first.histogram();
+        let counts: Vec<(u64, u64)> = histogram
+            .buckets
+            .iter()
+            .map(|b| (b.lower_ns, b.count))
+            .collect();
+        let lower = |ns| PassTimings::lower_bound(PassTimings::bucket(ns)) as u64;
+        assert_eq!(
+            counts,
+            vec![
+                (lower(100), 3),
+                (lower(5000), 1),
+                (lower(1_000_000), 1),
+                (lower(u64::MAX), 1)
+            ]
+        );
+        for bucket in &histogram.buckets {
+            assert!(bucket.lower_ns < bucket.upper_ns);
+        }
+        assert_eq!(histogram.buckets.last().unwrap().upper_ns, u64::MAX);
+    }
+}
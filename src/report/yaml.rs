@@ -0,0 +1,325 @@
+//! Minimal YAML emitter for the report structures.
+//!
+//! Values are serialized through `serde_json::Value` so the output carries exactly the fields
+//! the JSON format does (including `#[serde(skip)]` handling), then written as block-style YAML.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Serializes `value` as a YAML document
+pub fn to_string<T: Serialize>(value: &T) -> serde_json::Result<String> {
+    let value = serde_json::to_value(value)?;
+    let mut out = String::from("---\n");
+    match value {
+        Value::Object(ref map) if !map.is_empty() => write_block(&mut out, &value, 0),
+        Value::Array(ref items) if !items.is_empty() => write_block(&mut out, &value, 0),
+        _ => {
+            out.push_str(&scalar(&value));
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+/// Writes a non-empty mapping or sequence, one entry per line at `indent`
+fn write_block(out: &mut String, value: &Value, indent: usize) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                out.push_str(&pad);
+                out.push_str(&string(key));
+                out.push(':');
+                write_nested(out, value, indent);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                out.push_str(&pad);
+                out.push('-');
+                if is_block(item) {
+                    // Render the item one level deeper, then pull its first line up beside the dash
+                    let mut nested = String::new();
+                    write_block(&mut nested, item, indent + 2);
+                    out.push(' ');
+                    out.push_str(&nested[indent + 2..]);
+                } else {
+                    out.push(' ');
+                    out.push_str(&scalar(item));
+                    out.push('\n');
+                }
+            }
+        }
+        _ => unreachable!("write_block called with a scalar"),
+    }
+}
+
+/// Writes the value of a mapping entry whose key has already been written
+fn write_nested(out: &mut String, value: &Value, indent: usize) {
+    if is_block(value) {
+        out.push('\n');
+        write_block(out, value, indent + 2);
+    } else {
+        out.push(' ');
+        out.push_str(&scalar(value));
+        out.push('\n');
+    }
+}
+
+fn is_block(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => false,
+    }
+}
+
+/// Formats scalars and empty collections in flow style
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => string(s),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+    }
+}
+
+/// Writes a string plain when that is unambiguous, otherwise double-quoted. JSON string
+/// escaping is a subset of YAML's double-quoted style, so serde_json does the quoting.
+fn string(s: &str) -> String {
+    let reserved = matches!(
+        s.to_ascii_lowercase().as_str(),
+        "" | "~" | "null" | "true" | "false" | "yes" | "no" | "on" | "off"
+    );
+    let plain = !reserved
+        && s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || " _-./()@+".contains(c))
+        && !s.ends_with(' ')
+        && s.parse::<f64>().is_err();
+    if plain {
+        s.to_string()
+    } else {
+        serde_json::to_string(s).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Reads back the block-style subset `to_string` writes: one entry per line, nesting by
+    /// two-space indentation, and flow-style scalars
+    fn parse(yaml: &str) -> Value {
+        let lines: Vec<(usize, String)> = yaml
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let trimmed = line.trim_start_matches(' ');
+                (line.len() - trimmed.len(), trimmed.to_string())
+            })
+            .collect();
+        let first = &lines[0].1;
+        if lines.len() == 1 && !first.starts_with("- ") && split_key(first).is_none() {
+            return parse_scalar(first);
+        }
+        block(&mut lines.into_iter().peekable(), 0)
+    }
+
+    type Lines = std::iter::Peekable<std::vec::IntoIter<(usize, String)>>;
+
+    fn block(lines: &mut Lines, indent: usize) -> Value {
+        let is_sequence = lines.peek().unwrap().1.starts_with('-');
+        let mut items = Vec::new();
+        let mut map = serde_json::Map::new();
+
+        while let Some((level, line)) = lines.peek().cloned() {
+            if level < indent {
+                break;
+            }
+            assert_eq!(level, indent, "unexpected indentation at {:?}", line);
+            lines.next();
+
+            if is_sequence {
+                let rest = line.strip_prefix("- ").expect("sequence entry");
+                if rest.starts_with("- ") || split_key(rest).is_some() {
+                    // A nested block pulled up beside the dash: its lines continue two deeper
+                    let mut nested: Vec<(usize, String)> = vec![(indent + 2, rest.to_string())];
+                    while let Some((level, _)) = lines.peek() {
+                        if *level <= indent {
+                            break;
+                        }
+                        nested.push(lines.next().unwrap());
+                    }
+                    items.push(block(&mut nested.into_iter().peekable(), indent + 2));
+                } else {
+                    items.push(parse_scalar(rest));
+                }
+            } else {
+                let (key, rest) = split_key(&line).expect("mapping entry");
+                let value = if rest.is_empty() {
+                    block(lines, indent + 2)
+                } else {
+                    parse_scalar(rest)
+                };
+                map.insert(key, value);
+            }
+        }
+
+        if is_sequence {
+            Value::Array(items)
+        } else {
+            Value::Object(map)
+        }
+    }
+
+    /// Splits `key: value` (or `key:` before a nested block), with plain or quoted keys
+    fn split_key(line: &str) -> Option<(String, &str)> {
+        let end = if let Some(quoted) = line.strip_prefix('"') {
+            let mut escaped = false;
+            let close = quoted.char_indices().find(|&(_, c)| {
+                let close = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                close
+            })?;
+            close.0 + 2
+        } else {
+            line.find(':')?
+        };
+        let rest = line[end..].strip_prefix(':')?;
+        let key = match parse_scalar(&line[..end]) {
+            Value::String(key) => key,
+            _ => return None,
+        };
+        Some((key, rest.trim_start()))
+    }
+
+    fn parse_scalar(s: &str) -> Value {
+        match s {
+            "null" => Value::Null,
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "[]" => json!([]),
+            "{}" => json!({}),
+            _ if s.starts_with('"') => serde_json::from_str(s).unwrap(),
+            _ => serde_json::from_str::<serde_json::Number>(s)
+                .map(Value::Number)
+                .unwrap_or_else(|_| Value::String(s.to_string())),
+        }
+    }
+
+    #[test]
+    fn round_trips_awkward_strings() {
+        let value = json!({
+            "machine": {
+                "hostname": "build-01.example.com:8080 # rack \"A\"",
+                "cpu_brand": "Intel(R) Xeon(R) Gold 6338 CPU @ 2.00GHz",
+                "mallow_version": "0.1.0",
+            },
+            "errors": [
+                "Failed to bind: Operation not permitted (os error 1)",
+                "line one\nline two\ttabbed \\ backslash",
+                "- looks like a list item",
+                "  leading and trailing spaces  ",
+            ],
+            "lookalikes": ["yes", "No", "null", "~", "", "1e3", "0x10", "12.50", "true"],
+            "unicode": "Ryzen™ 9 — 7950X",
+            "numbers": [0, -3, 12.5, 1e300, u64::MAX],
+            "empty": {"list": [], "map": {}},
+            "nested": [[1, [2, 3]], {"a: b": {"c": [{"d": null}]}}],
+        });
+
+        let yaml = to_string(&value).unwrap();
+        assert_eq!(parse(&yaml), value, "{}", yaml);
+    }
+
+    #[test]
+    fn quotes_only_what_yaml_would_misread() {
+        assert_eq!(string("ScalarRead"), "ScalarRead");
+        assert_eq!(string("Intel(R) Core(TM) i7"), "Intel(R) Core(TM) i7");
+        for s in ["yes", "Off", "1.5", "key: value", "# comment", "", "a\"b"] {
+            assert!(string(s).starts_with('"'), "{:?} was left plain", s);
+        }
+    }
+
+    #[test]
+    fn round_trips_top_level_scalars() {
+        for value in [
+            json!(null),
+            json!("plain"),
+            json!("needs: quoting"),
+            json!(3),
+        ] {
+            assert_eq!(parse(&to_string(&value).unwrap()), value);
+        }
+    }
+
+    #[test]
+    fn round_trips_benchmark_results() {
+        use crate::report::BenchmarkResults;
+        use core_affinity::CoreId;
+
+        let result = json!({
+            "size_mib": 0.25,
+            "stride_bytes": 64,
+            "bandwidth_gib_s": 12.5,
+            "simd_enabled": true,
+            "parallel_enabled": true,
+            "affinity_enabled": true,
+            "hugepages_enabled": false,
+            "iterations": 20,
+            "warmup_iterations": 5,
+            "threads": 2,
+            "per_thread": [6.0, 6.5],
+            "elapsed_secs": 0.25,
+            "interrupted": false,
+            "timestamp": "2026-10-15T04:44:04Z",
+            "kernel_name": "SimdRead",
+            "level": "L2",
+            "ipc": 1.25,
+        });
+        let mut results: BenchmarkResults = serde_json::from_value(json!({
+            "schema_version": 3,
+            "results": [result, result],
+            "config": {
+                "size": 256 << 10,
+                "stride": 16,
+                "prefetch_distance": 0,
+                "unroll": 4,
+                "alignment": 64,
+                "hugepages": false,
+                "prefault": true,
+                "duration_secs": 0.25,
+                "warmup_iterations": 5,
+                "kernel": "SimdRead",
+                "thread_count": 2,
+                "cpu_cache_info": {"l1d_line_size": 64},
+            },
+            "machine": {
+                "hostname": "rack-7: node \"3\"",
+                "cpu_brand": "Intel(R) Xeon(R) Gold 6338 CPU @ 2.00GHz",
+                "logical_cores": 64,
+                "physical_cores": 32,
+                "mallow_version": "0.1.0",
+            },
+        }))
+        .unwrap();
+        results.config.core_ids = vec![CoreId { id: 3 }, CoreId { id: 5 }];
+
+        let yaml = to_string(&results).unwrap();
+        assert!(!yaml.contains("core_ids"), "{}", yaml);
+        let back: BenchmarkResults = serde_json::from_value(parse(&yaml)).unwrap();
+
+        // core_ids is skipped, so it comes back empty and everything else unchanged
+        assert!(back.config.core_ids.is_empty());
+        assert_eq!(back.machine.hostname, results.machine.hostname);
+        assert_eq!(back.results[1].per_thread, vec![6.0, 6.5]);
+        assert_eq!(
+            serde_json::to_value(&back).unwrap(),
+            serde_json::to_value(&results).unwrap()
+        );
+    }
+}
@@ -1,7 +1,7 @@
-use raw_cpuid::CpuId;
-use serde::Serialize;
+use raw_cpuid::{CacheInfoType, CpuId, DatType};
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CacheInfo {
     pub l1d_line_size: Option<usize>,
     pub l1d_size_kb: Option<usize>,
@@ -15,6 +15,44 @@ pub struct CacheInfo {
     pub l3_size_kb: Option<usize>,
     pub l3_sets: Option<usize>,
     pub l3_associativity: Option<usize>,
+    pub dtlb_4k_entries: Option<usize>,
+    pub dtlb_2m_entries: Option<usize>,
+    pub stlb_entries: Option<usize>,
+}
+
+impl CacheInfo {
+    /// Working-set sizes in bytes for each level of the hierarchy: half of L1D, L2 and L3
+    /// so the buffer stays resident, and 4x L3 so it spills to DRAM. Returns `None` if any
+    /// cache level could not be detected.
+    pub fn level_working_sets(&self) -> Option<Vec<(&'static str, usize)>> {
+        let l1d = self.l1d_size_kb? * 1024;
+        let l2 = self.l2_size_kb? * 1024;
+        let l3 = self.l3_size_kb? * 1024;
+        Some(vec![
+            ("L1", l1d / 2),
+            ("L2", l2 / 2),
+            ("L3", l3 / 2),
+            ("DRAM", l3 * 4),
+        ])
+    }
+
+    /// Smallest detected cache level that holds `bytes`, or "DRAM" if it exceeds the largest
+    /// one. Returns `None` if no cache level could be detected.
+    pub fn fitting_level(&self, bytes: usize) -> Option<&'static str> {
+        let levels = [
+            ("L1", self.l1d_size_kb),
+            ("L2", self.l2_size_kb),
+            ("L3", self.l3_size_kb),
+        ];
+        if levels.iter().all(|(_, size)| size.is_none()) {
+            return None;
+        }
+        let level = levels
+            .into_iter()
+            .find(|(_, size)| size.is_some_and(|kb| bytes <= kb * 1024))
+            .map_or("DRAM", |(level, _)| level);
+        Some(level)
+    }
 }
 
 pub fn get_cpu_info() -> CacheInfo {
@@ -32,6 +70,9 @@ pub fn get_cpu_info() -> CacheInfo {
         l3_size_kb: None,
         l3_sets: None,
         l3_associativity: None,
+        dtlb_4k_entries: None,
+        dtlb_2m_entries: None,
+        stlb_entries: None,
     };
 
     if let Some(cparams) = cpuid.get_cache_parameters() {
@@ -66,5 +107,85 @@ pub fn get_cpu_info() -> CacheInfo {
         }
     }
 
+    // TLB geometry from the deterministic address translation leaf (0x18) on recent Intel
+    if let Some(dat) = cpuid.get_deterministic_address_translation_info() {
+        for tlb in dat {
+            let entries = tlb.ways() as usize * tlb.sets() as usize;
+            match (tlb.cache_level(), tlb.cache_type()) {
+                (1, DatType::DataTLB | DatType::LoadOnly) => {
+                    if tlb.has_4k_entries() {
+                        info.dtlb_4k_entries.get_or_insert(entries);
+                    }
+                    if tlb.has_2mb_entries() {
+                        info.dtlb_2m_entries.get_or_insert(entries);
+                    }
+                }
+                (2, DatType::UnifiedTLB) => {
+                    info.stlb_entries.get_or_insert(entries);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Legacy cache descriptors (leaf 0x02) on older Intel
+    if let Some(descriptors) = cpuid.get_cache_info() {
+        for descriptor in descriptors {
+            let desc = descriptor.desc();
+            let Some(entries) = descriptor_entries(desc) else {
+                continue;
+            };
+            match descriptor.typ {
+                CacheInfoType::DTLB | CacheInfoType::TLB if desc.contains("Data TLB") => {
+                    if desc.contains("4 KByte") {
+                        info.dtlb_4k_entries.get_or_insert(entries);
+                    }
+                    if desc.contains("2 MByte") || desc.contains("2-MByte") {
+                        info.dtlb_2m_entries.get_or_insert(entries);
+                    }
+                }
+                CacheInfoType::DTLB if desc.starts_with("DTLB") => {
+                    if desc.contains("4 KByte") || desc.contains("4k") {
+                        info.dtlb_4k_entries.get_or_insert(entries);
+                    }
+                    if desc.contains("2M") {
+                        info.dtlb_2m_entries.get_or_insert(entries);
+                    }
+                }
+                CacheInfoType::STLB => {
+                    info.stlb_entries.get_or_insert(entries);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // AMD reports TLBs in the extended leaves 0x80000005/0x80000006 (zero on Intel)
+    if let Some(l1) = cpuid.get_l1_cache_and_tlb_info() {
+        if l1.dtlb_4k_size() > 0 {
+            info.dtlb_4k_entries
+                .get_or_insert(l1.dtlb_4k_size() as usize);
+        }
+        if l1.dtlb_2m_4m_size() > 0 {
+            info.dtlb_2m_entries
+                .get_or_insert(l1.dtlb_2m_4m_size() as usize);
+        }
+    }
+    if let Some(l2) = cpuid.get_l2_l3_cache_and_tlb_info() {
+        if l2.dtlb_4k_size() > 0 {
+            info.stlb_entries.get_or_insert(l2.dtlb_4k_size() as usize);
+        }
+    }
+
     info
 }
+
+/// Extracts the entry count from a leaf 0x02 TLB descriptor such as
+/// "Data TLB: 4 KByte pages, 4-way set associative, 64 entries"
+fn descriptor_entries(desc: &str) -> Option<usize> {
+    desc.split(',')
+        .map(str::trim)
+        .find(|part| part.ends_with("entries"))
+        .and_then(|part| part.split_whitespace().next())
+        .and_then(|count| count.parse().ok())
+}
@@ -0,0 +1,44 @@
+use super::topology::Topology;
+use raw_cpuid::CpuId;
+use serde::{Deserialize, Serialize};
+
+/// Identifies the machine a benchmark ran on so archived results are self-describing
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct MachineInfo {
+    pub hostname: String,
+    pub cpu_brand: Option<String>,
+    pub logical_cores: usize,
+    #[serde(default)]
+    pub physical_cores: usize,
+    pub mallow_version: String,
+}
+
+pub fn get_machine_info(topology: &Topology) -> MachineInfo {
+    MachineInfo {
+        hostname: hostname(),
+        cpu_brand: CpuId::new()
+            .get_processor_brand_string()
+            .map(|brand| brand.as_str().trim().to_string()),
+        logical_cores: topology.logical_cores,
+        physical_cores: topology.physical_cores,
+        mallow_version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
+
+/// Returns the machine's hostname, or "unknown" if it cannot be determined
+pub fn hostname() -> String {
+    #[cfg(target_os = "linux")]
+    {
+        let mut buf = [0u8; 256];
+        // SAFETY: buf is valid for writes of buf.len() bytes
+        let ret = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+        if ret == 0 {
+            let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+            return String::from_utf8_lossy(&buf[..len]).into_owned();
+        }
+    }
+
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
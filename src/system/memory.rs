@@ -0,0 +1,54 @@
+/// Estimates theoretical peak DRAM bandwidth in GB/s (10^9 bytes per second) from the SMBIOS
+/// memory device entries the kernel exposes under `/sys/firmware/dmi/entries` (the same data
+/// `dmidecode --type 17` prints). Each populated DIMM is assumed to sit on its own channel, so
+/// this overestimates peak on boards with several DIMMs per channel. Reading the entries
+/// usually requires root; returns `None` if they are unavailable.
+#[cfg(target_os = "linux")]
+pub fn detect_peak_gbps() -> Option<f64> {
+    let mut total_mb_s = 0u64;
+
+    for entry in std::fs::read_dir("/sys/firmware/dmi/entries")
+        .ok()?
+        .flatten()
+    {
+        if !entry.file_name().to_string_lossy().starts_with("17-") {
+            continue;
+        }
+        let Ok(raw) = std::fs::read(entry.path().join("raw")) else {
+            continue;
+        };
+        let word = |offset: usize| {
+            raw.get(offset..offset + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]) as u64)
+        };
+        let formatted_len = *raw.get(1)? as usize;
+
+        // Size 0 means the slot is empty
+        if word(0x0C).unwrap_or(0) == 0 {
+            continue;
+        }
+        let data_width_bits = match word(0x0A) {
+            Some(w) if w != 0 && w != 0xFFFF => w,
+            _ => continue,
+        };
+        // Prefer the configured speed (SMBIOS 2.7+) over the module's rated speed
+        let configured = if formatted_len > 0x21 {
+            word(0x20)
+        } else {
+            None
+        };
+        let speed_mt_s = match configured.filter(|&s| s != 0).or(word(0x15)) {
+            Some(s) if s != 0 && s != 0xFFFF => s,
+            _ => continue,
+        };
+
+        total_mb_s += speed_mt_s * data_width_bits / 8;
+    }
+
+    (total_mb_s > 0).then(|| total_mb_s as f64 / 1000.0)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn detect_peak_gbps() -> Option<f64> {
+    None
+}
@@ -1 +1,9 @@
+pub mod clock;
 pub mod cpu_info;
+pub mod host;
+pub mod memory;
+pub mod numa;
+pub mod perf;
+pub mod sched;
+pub mod signal;
+pub mod topology;
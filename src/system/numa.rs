@@ -0,0 +1,69 @@
+use std::io;
+
+use super::topology::parse_cpu_list;
+
+/// A NUMA node with memory and the CPUs attached to it
+#[derive(Clone, Debug)]
+pub struct NumaNode {
+    pub id: usize,
+    pub cpus: Vec<usize>,
+}
+
+/// Lists the online NUMA nodes that have memory, from sysfs. Empty where sysfs is unavailable,
+/// and a single node on machines without NUMA.
+pub fn nodes() -> Vec<NumaNode> {
+    let Ok(list) = std::fs::read_to_string("/sys/devices/system/node/has_memory") else {
+        return Vec::new();
+    };
+    parse_cpu_list(&list)
+        .into_iter()
+        .map(|id| {
+            let path = format!("/sys/devices/system/node/node{}/cpulist", id);
+            let cpus = std::fs::read_to_string(path)
+                .map(|list| parse_cpu_list(&list))
+                .unwrap_or_default();
+            NumaNode { id, cpus }
+        })
+        .collect()
+}
+
+/// Restricts the calling thread's future page allocations to `node` with `set_mempolicy`.
+/// Pages already faulted in keep their placement, so call this before allocating and touching
+/// the buffer.
+pub fn bind_memory_to(node: usize) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        const MPOL_BIND: libc::c_int = 2;
+        const MASK_BITS: usize = 1024;
+
+        if node >= MASK_BITS {
+            return Err(io::Error::from(io::ErrorKind::InvalidInput));
+        }
+        let mut mask = [0 as libc::c_ulong; MASK_BITS / libc::c_ulong::BITS as usize];
+        let bits = libc::c_ulong::BITS as usize;
+        mask[node / bits] |= 1 << (node % bits);
+
+        // SAFETY: mask holds MASK_BITS bits and maxnode tells the kernel not to read past it
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_set_mempolicy,
+                MPOL_BIND,
+                mask.as_ptr(),
+                MASK_BITS as libc::c_ulong,
+            )
+        };
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = node;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "memory binding is only supported on Linux",
+        ))
+    }
+}
@@ -0,0 +1,295 @@
+use std::io;
+
+/// Hardware counter totals over a timed region. A counter the PMU does not provide is `None`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PerfCounts {
+    /// Last-level cache misses
+    pub cache_misses: Option<u64>,
+    pub instructions: Option<u64>,
+    pub cycles: Option<u64>,
+}
+
+impl PerfCounts {
+    /// Sums two sets of counts, keeping only the counters both have
+    pub fn add(&self, other: &PerfCounts) -> PerfCounts {
+        let sum = |a: Option<u64>, b: Option<u64>| Some(a? + b?);
+        PerfCounts {
+            cache_misses: sum(self.cache_misses, other.cache_misses),
+            instructions: sum(self.instructions, other.instructions),
+            cycles: sum(self.cycles, other.cycles),
+        }
+    }
+
+    /// Instructions retired per cycle, or `None` if either was not counted
+    pub fn ipc(&self) -> Option<f64> {
+        let (instructions, cycles) = (self.instructions?, self.cycles?);
+        (cycles > 0).then(|| instructions as f64 / cycles as f64)
+    }
+
+    /// Names of the counters that were not available
+    pub fn missing(&self) -> Vec<&'static str> {
+        [
+            ("LLC misses", self.cache_misses),
+            ("instructions", self.instructions),
+            ("cycles", self.cycles),
+        ]
+        .into_iter()
+        .filter_map(|(name, count)| count.is_none().then_some(name))
+        .collect()
+    }
+}
+
+/// Cache-miss, instruction and cycle counters for the calling thread, opened with
+/// `perf_event_open`. User-space only, so `perf_event_paranoid` up to 2 allows them.
+///
+/// The raw syscall stands in for the `perf-event` crate: three fixed hardware events read as
+/// plain totals need only the 64-byte version-0 attribute struct and three ioctls, and the
+/// crate would add a bindgen-generated binding of the whole perf ABI for that.
+pub struct PerfCounters {
+    /// One descriptor per `COUNTERS` entry, `None` where the PMU lacks that event
+    #[cfg(target_os = "linux")]
+    fds: [Option<libc::c_int>; 3],
+}
+
+/// Hardware events counted, in `PerfCounts` field order
+#[cfg(target_os = "linux")]
+const COUNTERS: [u64; 3] = [
+    linux::PERF_COUNT_HW_CACHE_MISSES,
+    linux::PERF_COUNT_HW_INSTRUCTIONS,
+    linux::PERF_COUNT_HW_CPU_CYCLES,
+];
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::io;
+
+    pub const PERF_TYPE_HARDWARE: u32 = 0;
+    pub const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+    pub const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+    pub const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+
+    const DISABLED: u64 = 1 << 0;
+    const EXCLUDE_KERNEL: u64 = 1 << 5;
+    const EXCLUDE_HV: u64 = 1 << 6;
+
+    // _IO('$', n)
+    pub const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+    pub const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+    pub const PERF_EVENT_IOC_RESET: libc::c_ulong = 0x2403;
+
+    const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+    /// The first 64 bytes of `struct perf_event_attr` (PERF_ATTR_SIZE_VER0), which every
+    /// kernel with perf support accepts
+    #[repr(C)]
+    struct PerfEventAttr {
+        kind: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+    }
+
+    /// Opens a disabled hardware counter for the calling thread on any CPU
+    pub fn open(config: u64) -> io::Result<libc::c_int> {
+        let attr = PerfEventAttr {
+            kind: PERF_TYPE_HARDWARE,
+            size: std::mem::size_of::<PerfEventAttr>() as u32,
+            config,
+            sample_period: 0,
+            sample_type: 0,
+            read_format: 0,
+            flags: DISABLED | EXCLUDE_KERNEL | EXCLUDE_HV,
+            wakeup_events: 0,
+            bp_type: 0,
+            config1: 0,
+        };
+        // SAFETY: attr is a valid, fully initialized perf_event_attr of the size it declares
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                &attr as *const PerfEventAttr,
+                0 as libc::pid_t,
+                -1 as libc::c_int,
+                -1 as libc::c_int,
+                PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(fd as libc::c_int)
+        }
+    }
+
+    pub fn ioctl(fd: libc::c_int, request: libc::c_ulong) {
+        // SAFETY: fd is an open perf event and the request takes no argument
+        unsafe { libc::ioctl(fd, request as _, 0) };
+    }
+
+    pub fn read(fd: libc::c_int) -> io::Result<u64> {
+        let mut value = 0u64;
+        // SAFETY: reading a perf event without read_format flags yields a single u64
+        let n = unsafe { libc::read(fd, &mut value as *mut u64 as *mut libc::c_void, 8) };
+        if n == 8 {
+            Ok(value)
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+impl PerfCounters {
+    /// Opens the counters in a disabled state, skipping any the PMU does not provide. Fails
+    /// only if none can be opened: the kernel or hypervisor exposes no PMU, or
+    /// `perf_event_paranoid` forbids access.
+    pub fn open() -> io::Result<Self> {
+        #[cfg(target_os = "linux")]
+        return Self::open_with(linux::open);
+        #[cfg(not(target_os = "linux"))]
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "hardware counters are only supported on Linux",
+        ))
+    }
+
+    /// Opens each of `COUNTERS` with `open`, keeping the first error to report if all fail
+    #[cfg(target_os = "linux")]
+    fn open_with(mut open: impl FnMut(u64) -> io::Result<libc::c_int>) -> io::Result<Self> {
+        // Build the struct as we go so Drop closes whatever was opened before returning an error
+        let mut counters = PerfCounters { fds: [None; 3] };
+        let mut first_error = None;
+        for (fd, config) in counters.fds.iter_mut().zip(COUNTERS) {
+            match open(config) {
+                Ok(opened) => *fd = Some(opened),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) if counters.fds.iter().all(Option::is_none) => Err(e),
+            _ => Ok(counters),
+        }
+    }
+
+    /// Zeroes and starts the counters
+    pub fn start(&self) {
+        #[cfg(target_os = "linux")]
+        for &fd in self.fds.iter().flatten() {
+            linux::ioctl(fd, linux::PERF_EVENT_IOC_RESET);
+            linux::ioctl(fd, linux::PERF_EVENT_IOC_ENABLE);
+        }
+    }
+
+    /// Stops the counters and returns their totals since `start`
+    pub fn stop(&self) -> io::Result<PerfCounts> {
+        #[cfg(target_os = "linux")]
+        {
+            for &fd in self.fds.iter().flatten() {
+                linux::ioctl(fd, linux::PERF_EVENT_IOC_DISABLE);
+            }
+            let read = |fd: Option<libc::c_int>| fd.map(linux::read).transpose();
+            Ok(PerfCounts {
+                cache_misses: read(self.fds[0])?,
+                instructions: read(self.fds[1])?,
+                cycles: read(self.fds[2])?,
+            })
+        }
+        #[cfg(not(target_os = "linux"))]
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for PerfCounters {
+    fn drop(&mut self) {
+        for &fd in self.fds.iter().flatten() {
+            // SAFETY: fd was returned by perf_event_open and is closed exactly once
+            unsafe { libc::close(fd) };
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    /// A real descriptor for `open_with` to own and close, standing in for a perf event
+    fn placeholder_fd() -> io::Result<libc::c_int> {
+        use std::os::fd::IntoRawFd;
+        Ok(std::fs::File::open("/dev/null")?.into_raw_fd())
+    }
+
+    fn unsupported() -> io::Error {
+        io::Error::from_raw_os_error(libc::ENOENT)
+    }
+
+    #[test]
+    fn counters_the_pmu_lacks_are_skipped() {
+        let counters = PerfCounters::open_with(|config| match config {
+            linux::PERF_COUNT_HW_CACHE_MISSES => Err(unsupported()),
+            _ => placeholder_fd(),
+        })
+        .unwrap();
+        assert!(counters.fds[0].is_none());
+        assert!(counters.fds[1].is_some() && counters.fds[2].is_some());
+    }
+
+    #[test]
+    fn opening_fails_when_no_counter_is_available() {
+        let mut attempts = 0;
+        let err = PerfCounters::open_with(|_| {
+            attempts += 1;
+            Err(io::Error::from_raw_os_error(libc::EACCES + attempts - 1))
+        })
+        .err()
+        .unwrap();
+        assert_eq!(attempts, 3);
+        // The first failure is the one reported
+        assert_eq!(err.raw_os_error(), Some(libc::EACCES));
+    }
+
+    #[test]
+    fn real_counters_open_or_fail_cleanly() {
+        // Virtual machines and containers often expose no PMU at all
+        match PerfCounters::open() {
+            Ok(counters) => {
+                counters.start();
+                let counts = counters.stop().unwrap();
+                assert!(counts.missing().len() < 3);
+            }
+            Err(e) => assert!(e.raw_os_error().is_some(), "{}", e),
+        }
+    }
+
+    #[test]
+    fn missing_counters_drop_out_of_totals_and_ipc() {
+        let full = PerfCounts {
+            cache_misses: Some(10),
+            instructions: Some(300),
+            cycles: Some(200),
+        };
+        let no_misses = PerfCounts {
+            cache_misses: None,
+            ..full
+        };
+        let total = full.add(&no_misses);
+        assert_eq!(total.cache_misses, None);
+        assert_eq!(total.instructions, Some(600));
+        assert_eq!(total.ipc(), Some(1.5));
+        assert_eq!(total.missing(), vec!["LLC misses"]);
+
+        let no_cycles = PerfCounts {
+            cycles: None,
+            ..full
+        };
+        assert_eq!(no_cycles.ipc(), None);
+        assert_eq!(no_cycles.cache_misses, Some(10));
+    }
+}
@@ -0,0 +1,28 @@
+use std::io;
+
+/// Moves the calling thread to the `SCHED_FIFO` real-time class at `priority` (1 to 99), so
+/// only higher-priority real-time work preempts it. Needs `CAP_SYS_NICE` or an `RLIMIT_RTPRIO`
+/// that allows the priority.
+pub fn set_realtime_priority(priority: u8) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        let param = libc::sched_param {
+            sched_priority: priority as libc::c_int,
+        };
+        // SAFETY: param is a valid sched_param; pid 0 is the calling thread on Linux
+        let ret = unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) };
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = priority;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "real-time scheduling is only supported on Linux",
+        ))
+    }
+}
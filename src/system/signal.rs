@@ -0,0 +1,30 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Installs a SIGINT handler that records the interrupt so a running benchmark can stop early
+/// and still report. A second Ctrl-C exits immediately in case a thread is wedged.
+pub fn install_interrupt_handler() {
+    #[cfg(target_os = "linux")]
+    {
+        extern "C" fn handle_sigint(_: libc::c_int) {
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                // SAFETY: _exit is async-signal-safe
+                unsafe { libc::_exit(130) };
+            }
+        }
+
+        // SAFETY: the handler only touches an atomic and calls async-signal-safe functions
+        unsafe {
+            libc::signal(
+                libc::SIGINT,
+                handle_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+    }
+}
+
+/// Returns true once Ctrl-C has been pressed
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,124 @@
+use core_affinity::CoreId;
+use raw_cpuid::{CpuId, TopologyType};
+
+/// Logical vs physical core layout of the CPUs available to this process
+#[derive(Clone)]
+pub struct Topology {
+    pub logical_cores: usize,
+    pub physical_cores: usize,
+    /// One logical CPU per physical core, used to avoid pinning threads to SMT siblings
+    pub primary_threads: Vec<CoreId>,
+}
+
+/// Parses a Linux CPU list such as `0-3,8,10-11` into CPU ids
+pub fn parse_cpu_list(list: &str) -> Vec<usize> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) {
+                    cpus.extend(start..=end);
+                }
+            }
+            None => cpus.extend(part.parse::<usize>().ok()),
+        }
+    }
+    cpus
+}
+
+/// Reads the SMT siblings of `cpu` from sysfs
+fn thread_siblings(cpu: usize) -> Option<Vec<usize>> {
+    let path = format!(
+        "/sys/devices/system/cpu/cpu{}/topology/thread_siblings_list",
+        cpu
+    );
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|list| parse_cpu_list(&list))
+}
+
+/// Reads the CPUs sharing `cpu`'s L3 cache from sysfs
+fn l3_siblings(cpu: usize) -> Option<Vec<usize>> {
+    let dir = format!("/sys/devices/system/cpu/cpu{}/cache", cpu);
+    std::fs::read_dir(dir).ok()?.flatten().find_map(|entry| {
+        let level = std::fs::read_to_string(entry.path().join("level")).ok()?;
+        if level.trim() != "3" {
+            return None;
+        }
+        std::fs::read_to_string(entry.path().join("shared_cpu_list"))
+            .ok()
+            .map(|list| parse_cpu_list(&list))
+    })
+}
+
+/// Groups `core_ids` by the L3 cache they share, one group per CCX on chiplet CPUs, ordered by
+/// their lowest CPU id. Empty where sysfs does not describe the L3.
+pub fn l3_domains(core_ids: &[CoreId]) -> Vec<Vec<CoreId>> {
+    let mut domains: Vec<(Vec<usize>, Vec<CoreId>)> = Vec::new();
+    for &core in core_ids {
+        let Some(siblings) = l3_siblings(core.id) else {
+            return Vec::new();
+        };
+        match domains.iter_mut().find(|(shared, _)| *shared == siblings) {
+            Some((_, cores)) => cores.push(core),
+            None => domains.push((siblings, vec![core])),
+        }
+    }
+    domains.sort_by_key(|(_, cores)| cores.iter().map(|c| c.id).min());
+    domains.into_iter().map(|(_, cores)| cores).collect()
+}
+
+/// Number of hardware threads per core from cpuid leaf 0x0B
+fn threads_per_core() -> usize {
+    CpuId::new()
+        .get_extended_topology_info()
+        .and_then(|mut levels| levels.find(|level| level.level_type() == TopologyType::SMT))
+        .map(|level| level.processors() as usize)
+        .filter(|&n| n > 0)
+        .unwrap_or(1)
+}
+
+/// Groups `core_ids` into physical cores using sysfs sibling lists, falling back to cpuid's
+/// threads-per-core (assuming siblings have adjacent ids) where sysfs is unavailable
+pub fn detect_topology(core_ids: &[CoreId]) -> Topology {
+    let available: Vec<usize> = core_ids.iter().map(|c| c.id).collect();
+
+    let primary_threads: Vec<CoreId> = if core_ids.iter().all(|c| thread_siblings(c.id).is_some()) {
+        // Keep a CPU unless one of its siblings with a lower id is also available
+        core_ids
+            .iter()
+            .filter(|c| {
+                thread_siblings(c.id)
+                    .unwrap_or_default()
+                    .iter()
+                    .all(|s| *s >= c.id || !available.contains(s))
+            })
+            .copied()
+            .collect()
+    } else {
+        core_ids
+            .iter()
+            .step_by(threads_per_core())
+            .copied()
+            .collect()
+    };
+
+    Topology {
+        logical_cores: core_ids.len(),
+        physical_cores: primary_threads.len(),
+        primary_threads,
+    }
+}
+
+/// CPU the calling thread is running on right now, from `sched_getcpu`. `None` where that is
+/// unavailable.
+pub fn current_cpu() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: sched_getcpu has no preconditions
+        let cpu = unsafe { libc::sched_getcpu() };
+        (cpu >= 0).then_some(cpu as usize)
+    }
+    #[cfg(not(target_os = "linux"))]
+    None
+}